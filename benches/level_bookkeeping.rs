@@ -0,0 +1,91 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rltk::{a_star_search, Point, RandomNumberGenerator};
+
+use rust_roguelike::camera::Camera;
+use rust_roguelike::level::{for_each_drawn_tile, Level, TileType};
+use rust_roguelike::map_builders::{CellularAutomataBuilder, MapBuilder};
+
+const SIZE: (i32, i32) = (200, 200);
+const SEED: u64 = 1234;
+
+/// Big cave level, fully revealed, with the stairs as far from the start as it gets
+fn big_level() -> (Level, (i32, i32)) {
+    let mut rng = RandomNumberGenerator::seeded(SEED);
+    let mut builder = CellularAutomataBuilder::new(SIZE.0, SIZE.1, 1);
+    builder.build_map(&mut rng);
+
+    let mut level = builder.get_level();
+    level.index_walls();
+    for idx in 0..level.tiles.len() {
+        level.reveal_tile(idx);
+    }
+    (level, builder.get_player_start())
+}
+
+fn draw_tiles(c: &mut Criterion) {
+    let (level, start) = big_level();
+    let camera = Camera::centered_on(Point::new(start.0, start.1), level.width, level.height);
+
+    c.bench_function("draw_tiles (viewport of 200x200)", |b| {
+        b.iter(|| {
+            let mut drawn = 0;
+            for_each_drawn_tile(&level, &camera, |x, y, glyph, fg| {
+                black_box((x, y, glyph, fg));
+                drawn += 1;
+            });
+            drawn
+        })
+    });
+}
+
+fn is_valid_exit(c: &mut Criterion) {
+    let (level, _) = big_level();
+
+    c.bench_function("is_valid_exit (every tile of 200x200)", |b| {
+        b.iter(|| {
+            let mut valid = 0;
+            for y in 0..level.height {
+                for x in 0..level.width {
+                    if level.is_valid_exit(black_box(x), black_box(y)) {
+                        valid += 1;
+                    }
+                }
+            }
+            valid
+        })
+    });
+}
+
+fn a_star(c: &mut Criterion) {
+    let (level, start) = big_level();
+    let start_idx = level.xy_idx(start.0, start.1);
+    let stairs_idx = level
+        .tiles
+        .iter()
+        .position(|tile| *tile == TileType::DownStairs)
+        .expect("Builder should place the stairs");
+
+    c.bench_function("a_star_search (start to stairs on 200x200)", |b| {
+        b.iter(|| a_star_search(black_box(start_idx), black_box(stairs_idx), &level).success)
+    });
+}
+
+fn map_indexing(c: &mut Criterion) {
+    let (mut level, _) = big_level();
+    let floor: Vec<usize> = (0..level.tiles.len())
+        .filter(|&idx| level.tiles[idx] == TileType::Floor)
+        .step_by(50)
+        .collect();
+
+    c.bench_function("block_walls_only + block_tile (200x200)", |b| {
+        b.iter(|| {
+            level.block_walls_only();
+            for idx in floor.iter() {
+                level.block_tile(*idx);
+            }
+        })
+    });
+}
+
+criterion_group!(benches, draw_tiles, is_valid_exit, a_star, map_indexing);
+criterion_main!(benches);
@@ -0,0 +1,117 @@
+use rltk::Point;
+use specs::World;
+
+use crate::level::Level;
+
+/// Part of the console the level is drawn into, the rest of it belongs to the UI panel
+pub const VIEWPORT_WIDTH: i32 = 80;
+pub const VIEWPORT_HEIGHT: i32 = 43;
+
+/// Window into the level, translating between world and screen coordinates.
+/// Follows the player, but stops at the level edges so no space is wasted on the void.
+#[derive(Copy, Clone)]
+pub struct Camera {
+    /// World coordinates of the top left corner of the viewport
+    pub min_x: i32,
+    pub min_y: i32,
+}
+
+impl Camera {
+    /// Camera centred on the player position, clamped to the level
+    pub fn new(ecs: &World) -> Self {
+        let player_pos = ecs.fetch::<Point>();
+        let level = ecs.fetch::<Level>();
+        Camera::centered_on(*player_pos, level.width, level.height)
+    }
+
+    pub fn centered_on(center: Point, level_width: i32, level_height: i32) -> Self {
+        let max_x = i32::max(0, level_width - VIEWPORT_WIDTH);
+        let max_y = i32::max(0, level_height - VIEWPORT_HEIGHT);
+        Camera {
+            min_x: (center.x - VIEWPORT_WIDTH / 2).clamp(0, max_x),
+            min_y: (center.y - VIEWPORT_HEIGHT / 2).clamp(0, max_y),
+        }
+    }
+
+    /// Rets None if the world position is outside of the viewport
+    pub fn world_to_screen(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (screen_x, screen_y) = (x - self.min_x, y - self.min_y);
+        if Camera::is_on_viewport(screen_x, screen_y) {
+            Some((screen_x, screen_y))
+        } else {
+            None
+        }
+    }
+
+    /// Rets None if the screen position is outside of the viewport (e.g. over the UI panel)
+    pub fn screen_to_world(&self, screen_x: i32, screen_y: i32) -> Option<(i32, i32)> {
+        if Camera::is_on_viewport(screen_x, screen_y) {
+            Some((screen_x + self.min_x, screen_y + self.min_y))
+        } else {
+            None
+        }
+    }
+
+    fn is_on_viewport(screen_x: i32, screen_y: i32) -> bool {
+        (0..VIEWPORT_WIDTH).contains(&screen_x) && (0..VIEWPORT_HEIGHT).contains(&screen_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_and_screen_positions_translate_both_ways() {
+        let camera = Camera::centered_on(Point::new(100, 50), 200, 100);
+
+        assert_eq!((camera.min_x, camera.min_y), (60, 29));
+        assert_eq!(camera.world_to_screen(100, 50), Some((40, 21)));
+        assert_eq!(camera.screen_to_world(40, 21), Some((100, 50)));
+        assert_eq!(camera.world_to_screen(60, 29), Some((0, 0)));
+        assert_eq!(
+            camera.world_to_screen(60 + VIEWPORT_WIDTH - 1, 29 + VIEWPORT_HEIGHT - 1),
+            Some((VIEWPORT_WIDTH - 1, VIEWPORT_HEIGHT - 1))
+        );
+    }
+
+    #[test]
+    fn positions_off_the_viewport_have_no_counterpart() {
+        let camera = Camera::centered_on(Point::new(100, 50), 200, 100);
+
+        assert_eq!(camera.world_to_screen(59, 50), None);
+        assert_eq!(camera.world_to_screen(60 + VIEWPORT_WIDTH, 50), None);
+        assert_eq!(camera.world_to_screen(100, 29 + VIEWPORT_HEIGHT), None);
+        // The UI panel below the viewport
+        assert_eq!(camera.screen_to_world(10, VIEWPORT_HEIGHT), None);
+        assert_eq!(camera.screen_to_world(-1, 0), None);
+    }
+
+    #[test]
+    fn camera_stops_at_the_level_edges() {
+        let top_left = Camera::centered_on(Point::new(2, 3), 200, 100);
+        let bottom_right = Camera::centered_on(Point::new(199, 99), 200, 100);
+
+        assert_eq!((top_left.min_x, top_left.min_y), (0, 0));
+        assert_eq!(top_left.world_to_screen(2, 3), Some((2, 3)));
+        assert_eq!(
+            (bottom_right.min_x, bottom_right.min_y),
+            (200 - VIEWPORT_WIDTH, 100 - VIEWPORT_HEIGHT)
+        );
+        assert_eq!(
+            bottom_right.world_to_screen(199, 99),
+            Some((VIEWPORT_WIDTH - 1, VIEWPORT_HEIGHT - 1))
+        );
+    }
+
+    #[test]
+    fn levels_smaller_than_the_viewport_are_drawn_from_the_corner() {
+        let camera = Camera::centered_on(Point::new(25, 15), 50, 30);
+
+        assert_eq!((camera.min_x, camera.min_y), (0, 0));
+        assert_eq!(camera.world_to_screen(25, 15), Some((25, 15)));
+        assert_eq!(camera.world_to_screen(49, 29), Some((49, 29)));
+        // Past the level, but still on the viewport: callers check the level bounds themselves
+        assert_eq!(camera.screen_to_world(60, 35), Some((60, 35)));
+    }
+}
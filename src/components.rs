@@ -1,38 +1,83 @@
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use specs_derive::Component;
 
+pub mod ai;
 pub mod combat;
+pub mod companion;
+pub mod equipment;
+pub mod experience;
+pub mod faction;
+pub mod initiative;
+pub mod inventory;
 pub mod position;
 pub mod renderable;
+pub mod status;
 pub mod viewshed;
 
+pub use ai::*;
 pub use combat::*;
+pub use companion::*;
+pub use equipment::*;
+pub use experience::*;
+pub use faction::*;
+pub use initiative::*;
+pub use inventory::*;
 pub use position::*;
 pub use renderable::*;
+pub use status::*;
 pub use viewshed::*;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct LeftMover {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct PlayerChar {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct MonsterChar {}
 
 /// Anyone with this comp has a name we can show to the player
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct GameplayName {
     pub name: String,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct TileBlocker {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Item {}
 
-#[derive(Component, Debug)]
+/// Item is destroyed once it has been used
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Consumable {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Healer {
     pub heal_amount: u32,
 }
+
+/// Deals damage to whoever the item is used on, defense doesn't help against it
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsDamage {
+    pub damage: i32,
+}
+
+/// Item is used on a target tile up to `range` away, rather than on its user
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ranged {
+    pub range: i32,
+}
+
+/// Item hits everyone within `radius` of the target tile
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct AreaOfEffect {
+    pub radius: i32,
+}
+
+/// Item leaves whoever it hits confused for a number of turns
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Confusion {
+    pub turns: i32,
+}
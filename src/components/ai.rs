@@ -0,0 +1,51 @@
+use rltk::Point;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs_derive::Component;
+
+/// Monsters flee once their hp drops below this fraction of their max hp
+pub const FLEE_HP_FRACTION: f32 = 0.25;
+
+/// What a monster is up to
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AiState {
+    /// Doesn't see anyone hostile, wanders or sleeps
+    Idle,
+    /// Sees a hostile and goes for the nearest one
+    Hunting,
+    /// Lost sight of its target, heads for where it was last seen
+    Searching,
+    /// Too hurt to fight, runs from its target
+    Fleeing,
+}
+
+/// What a monster does while Idle
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleBehaviour {
+    /// Every now and then drifts a step towards the nearest item lying around,
+    /// or takes a random step once there's none to go to
+    #[default]
+    Wander,
+    /// Stays put until it notices someone hostile
+    Sleep,
+}
+
+/// State and memory of a monster's AI
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MonsterAI {
+    pub state: AiState,
+    pub idle: IdleBehaviour,
+    /// Tile the target was last seen on, forgotten once the search there is over
+    pub last_seen: Option<Point>,
+}
+
+impl MonsterAI {
+    pub fn new(idle: IdleBehaviour) -> Self {
+        MonsterAI {
+            state: AiState::Idle,
+            idle,
+            last_seen: None,
+        }
+    }
+}
@@ -1,7 +1,11 @@
+use rltk::Point;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
 use specs::prelude::*;
-use specs_derive::Component;
+use specs::saveload::{ConvertSaveload, Marker};
+use specs_derive::{Component, ConvertSaveload};
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct CombatStats {
     pub max_hp: u16,
     pub hp: i32,
@@ -10,25 +14,51 @@ pub struct CombatStats {
 }
 
 /// Indicator that the owning entity wants to attack a target
-#[derive(Component, Debug, /*ConvertSaveload,*/ Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct MeleeAttackIntent {
     pub target: Entity,
 }
 
-#[derive(Component, Debug)]
+/// Can shoot at whatever it sees within range
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RangedAttacker {
+    pub range: i32,
+    pub power: u16,
+}
+
+/// Indicator that the owning entity wants to shoot at a tile.
+/// The shot hits whoever stands first on the line of fire, which isn't necessarily at the target.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RangedAttackIntent {
+    pub target: Point,
+}
+
+/// Damage waiting for the DamageSystem. It never outlives a turn, so it isn't saved.
+#[derive(Component, Debug, Clone)]
 pub struct IncomingDamage {
     /// i32, cause the damage may be negative (healing)
     pub amount: Vec<i32>,
+    /// Whoever dealt the damage, in order. Poison counts as dealt by whoever applied it,
+    /// healing leaves no trace here.
+    pub sources: Vec<Entity>,
 }
 
 impl IncomingDamage {
-    pub fn new(store: &mut WriteStorage<IncomingDamage>, victim: Entity, amount: i32) {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        store: &mut WriteStorage<IncomingDamage>,
+        victim: Entity,
+        amount: i32,
+        source: Option<Entity>,
+    ) {
         if let Some(inc_dmg) = store.get_mut(victim) {
             // Add our damage to the list of already-existing pieces of damage
             inc_dmg.amount.push(amount);
+            inc_dmg.sources.extend(source);
         } else {
             let dmg = IncomingDamage {
                 amount: vec![amount],
+                sources: source.into_iter().collect(),
             };
             store
                 .insert(victim, dmg)
@@ -36,3 +66,9 @@ impl IncomingDamage {
         }
     }
 }
+
+/// Who dealt the killing blow. Only lives until the dead are cleaned up, so it isn't saved.
+#[derive(Component, Debug, Clone)]
+pub struct KilledBy {
+    pub killer: Entity,
+}
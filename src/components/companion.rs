@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{ConvertSaveload, Marker};
+use specs_derive::{Component, ConvertSaveload};
+
+/// Companions stick within this distance of the player while following
+pub const FOLLOW_DISTANCE: f32 = 2.0;
+/// Following companions give up on a fight once the player is farther away than this
+pub const FOLLOW_LEASH: f32 = 8.0;
+
+/// What the player told a companion to do
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum CompanionOrder {
+    /// Sticks to the player, fighting whatever hostile comes close. Comes along downstairs.
+    Follow,
+    /// Holds its ground, only fighting back what stands next to it. Left behind downstairs.
+    Stay,
+    /// Goes for its CompanionTarget until it drops, then follows again
+    Attack,
+}
+
+impl CompanionOrder {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            CompanionOrder::Follow => "following",
+            CompanionOrder::Stay => "staying",
+            CompanionOrder::Attack => "attacking",
+        }
+    }
+}
+
+/// Ally of the player, acting on the player's orders instead of a MonsterAI
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Companion {
+    pub order: CompanionOrder,
+    /// Set once the player gave it its first order. Until then it can only be ordered in sight.
+    pub recruited: bool,
+}
+
+/// Who a companion under the Attack order goes for
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct CompanionTarget {
+    pub target: Entity,
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{ConvertSaveload, Marker};
+use specs_derive::{Component, ConvertSaveload};
+
+use super::RangedAttacker;
+
+/// Where an item is worn. Each owner can have a single item equipped per slot.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EquipmentSlot {
+    Weapon,
+    Shield,
+    Armor,
+    Ranged,
+}
+
+/// Item can be worn in `slot`
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+/// Item is worn by `owner`. It stays in their backpack meanwhile.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
+/// Added to the owner's power while equipped
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MeleePowerBonus {
+    pub power: i32,
+}
+
+/// Added to the owner's defense while equipped
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct DefenseBonus {
+    pub defense: i32,
+}
+
+/// Lets the owner shoot while equipped, unless they already can on their own
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RangedWeapon {
+    pub range: i32,
+    pub power: u16,
+}
+
+/// Indicator that the owning entity wants to wear a carried item, swapping out whatever is in its slot
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToEquip {
+    pub item: Entity,
+}
+
+/// Indicator that the owning entity wants to take off a worn item
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToUnequip {
+    pub item: Entity,
+}
+
+/// Sum of the power bonuses of everything `owner` wears
+pub fn power_bonus_of(
+    owner: Entity,
+    equipped: &ReadStorage<Equipped>,
+    bonuses: &ReadStorage<MeleePowerBonus>,
+) -> i32 {
+    (equipped, bonuses)
+        .join()
+        .filter(|(worn, _)| worn.owner == owner)
+        .map(|(_, bonus)| bonus.power)
+        .sum()
+}
+
+/// Range and power `owner` shoots with: its own, or else those of a worn ranged weapon
+pub fn ranged_attack_of(
+    owner: Entity,
+    attackers: &ReadStorage<RangedAttacker>,
+    equipped: &ReadStorage<Equipped>,
+    weapons: &ReadStorage<RangedWeapon>,
+) -> Option<RangedAttacker> {
+    attackers.get(owner).cloned().or_else(|| {
+        (equipped, weapons)
+            .join()
+            .find(|(worn, _)| worn.owner == owner)
+            .map(|(_, weapon)| RangedAttacker {
+                range: weapon.range,
+                power: weapon.power,
+            })
+    })
+}
+
+/// Sum of the defense bonuses of everything `owner` wears
+pub fn defense_bonus_of(
+    owner: Entity,
+    equipped: &ReadStorage<Equipped>,
+    bonuses: &ReadStorage<DefenseBonus>,
+) -> i32 {
+    (equipped, bonuses)
+        .join()
+        .filter(|(worn, _)| worn.owner == owner)
+        .map(|(_, bonus)| bonus.defense)
+        .sum()
+}
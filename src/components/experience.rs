@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs_derive::Component;
+
+use super::CombatStats;
+
+/// Gained on every level up
+pub const HP_PER_LEVEL: u16 = 5;
+pub const POWER_PER_LEVEL: u16 = 1;
+/// Player turns the level-up indicator stays on screen for
+pub const LEVEL_UP_FLASH_TURNS: i32 = 5;
+
+/// Total xp needed to reach `level`. Every level takes 20 xp more than the previous one.
+pub fn xp_for_level(level: i32) -> i32 {
+    10 * (level - 1) * level
+}
+
+/// Character level of whoever can grow stronger by killing things
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Experience {
+    pub level: i32,
+    /// Total xp gained so far, it doesn't reset on level up
+    pub xp: i32,
+    /// Turns left to show the level-up indicator for
+    pub level_up_flash: i32,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Experience {
+            level: 1,
+            xp: 0,
+            level_up_flash: 0,
+        }
+    }
+}
+
+impl Experience {
+    /// Adds the xp and raises `stats` for every level reached. Rets the number of levels gained.
+    pub fn gain(&mut self, xp: i32, stats: &mut CombatStats) -> i32 {
+        self.xp += xp;
+
+        let mut levels_gained = 0;
+        while self.xp >= xp_for_level(self.level + 1) {
+            self.level += 1;
+            levels_gained += 1;
+
+            stats.max_hp += HP_PER_LEVEL;
+            stats.hp += HP_PER_LEVEL as i32;
+            stats.power += POWER_PER_LEVEL;
+        }
+        if levels_gained > 0 {
+            self.level_up_flash = LEVEL_UP_FLASH_TURNS;
+        }
+        levels_gained
+    }
+}
+
+/// Xp awarded to whoever kills the owner
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct XpReward {
+    pub xp: i32,
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs_derive::Component;
+
+/// Faction the player belongs to, anyone fighting alongside them should join it too
+pub const PLAYER_FACTION: &str = "player";
+
+/// How the members of one faction treat those of another
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reaction {
+    /// Attacked on sight
+    Hostile,
+    /// Left alone
+    Neutral,
+    /// On the same side
+    Friendly,
+}
+
+/// Side an actor is on. How factions react to each other is looked up in the raws.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Faction {
+    pub name: String,
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs_derive::Component;
+
+/// Energy an actor needs to take a turn. A regular action (a step, an attack) costs exactly this.
+pub const ACTION_COST: i32 = 100;
+/// Speed of the player and of an average monster, i.e. one action every 10 ticks
+pub const NORMAL_SPEED: i32 = 10;
+
+/// Actors accumulate `speed` energy every tick and act once they have ACTION_COST of it,
+/// so faster ones get more turns than slower ones
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Initiative {
+    pub speed: i32,
+    pub energy: i32,
+}
+
+impl Initiative {
+    pub fn new(speed: i32) -> Self {
+        Initiative { speed, energy: 0 }
+    }
+
+    /// Pays for an action. Leftover energy carries over into the next turn.
+    pub fn spend(&mut self, cost: i32) {
+        self.energy -= cost;
+    }
+}
+
+/// Marks actors that have enough energy to act right now. Removed once they've acted.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MyTurn {}
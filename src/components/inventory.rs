@@ -0,0 +1,33 @@
+use rltk::Point;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{ConvertSaveload, Marker};
+use specs_derive::{Component, ConvertSaveload};
+
+/// Item is carried by `owner` rather than lying on the floor (it has no Position while carried)
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct InBackpack {
+    pub owner: Entity,
+}
+
+/// Indicator that `collected_by` wants to put `item` into their backpack
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToPickUp {
+    pub collected_by: Entity,
+    pub item: Entity,
+}
+
+/// Indicator that the owning entity wants to drop a carried item at its feet
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToDrop {
+    pub item: Entity,
+}
+
+/// Indicator that the owning entity wants to use a carried item.
+/// `target` is only set for Ranged items, the rest are used on the owner.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToUse {
+    pub item: Entity,
+    pub target: Option<Point>,
+}
@@ -1,8 +1,17 @@
+use rltk::Point;
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use specs_derive::Component;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
+
+/// Actor wants to step onto `destination` this turn.
+/// MovementSystem decides who actually gets to move when several want the same tile.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MoveIntent {
+    pub destination: Point,
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{ConvertSaveload, Marker};
+use specs_derive::Component;
+use std::mem;
+
+/// What a lasting effect does, along with its per-turn strength
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum StatusKind {
+    /// Stumbles around instead of acting
+    Confused,
+    /// Takes `damage` at the start of every turn
+    Poisoned { damage: i32 },
+    /// Heals `heal` at the start of every turn
+    Regenerating { heal: i32 },
+    /// Gains energy twice as fast
+    Hasted,
+}
+
+impl StatusKind {
+    /// Used in the log, e.g. "Orc is poisoned."
+    pub fn describe(&self) -> &'static str {
+        match self {
+            StatusKind::Confused => "confused",
+            StatusKind::Poisoned { .. } => "poisoned",
+            StatusKind::Regenerating { .. } => "regenerating",
+            StatusKind::Hasted => "hasted",
+        }
+    }
+
+    /// Rets true if both are the same effect, whatever their strength
+    pub fn same_kind(&self, other: &StatusKind) -> bool {
+        mem::discriminant(self) == mem::discriminant(other)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Turns of the affected entity (not world ticks) the effect lasts for
+    pub turns: i32,
+    /// Whoever put the effect on, credited with the damage it deals.
+    /// Saved through StatusEffects, which swaps it for its marker.
+    #[serde(skip)]
+    pub source: Option<Entity>,
+}
+
+/// Lasting effects on an entity, ticking down at the start of each of its turns
+#[derive(Component, Debug, Clone, Default)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+/// StatusEffects as written into the save file, each effect along with its source's marker
+#[derive(Serialize, Deserialize)]
+pub struct StatusEffectsData<M> {
+    effects: Vec<(StatusEffect, Option<M>)>,
+}
+
+/// Written by hand, as the derive can't handle the optional sources.
+/// Sources that are gone by the time of saving are dropped rather than failing the save.
+impl<M: Marker + Serialize> ConvertSaveload<M> for StatusEffects
+where
+    for<'de> M: Deserialize<'de>,
+{
+    type Data = StatusEffectsData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        let effects = self
+            .effects
+            .iter()
+            .map(|effect| (*effect, effect.source.and_then(&mut ids)))
+            .collect();
+        Ok(StatusEffectsData { effects })
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        let effects = data
+            .effects
+            .into_iter()
+            .map(|(effect, source)| StatusEffect {
+                source: source.and_then(&mut ids),
+                ..effect
+            })
+            .collect();
+        Ok(StatusEffects { effects })
+    }
+}
+
+impl StatusEffects {
+    /// Adds the effect to the victim. An effect of the same kind gets replaced,
+    /// keeping whichever duration is longer, and credited to the latest source.
+    pub fn apply(store: &mut WriteStorage<StatusEffects>, victim: Entity, effect: StatusEffect) {
+        if let Some(statuses) = store.get_mut(victim) {
+            match statuses
+                .effects
+                .iter_mut()
+                .find(|existing| existing.kind.same_kind(&effect.kind))
+            {
+                Some(existing) => {
+                    existing.turns = i32::max(existing.turns, effect.turns);
+                    existing.kind = effect.kind;
+                    existing.source = effect.source;
+                }
+                None => statuses.effects.push(effect),
+            }
+        } else {
+            store
+                .insert(
+                    victim,
+                    StatusEffects {
+                        effects: vec![effect],
+                    },
+                )
+                .expect("Should be able to insert status effects to the victim");
+        }
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects
+            .iter()
+            .any(|effect| effect.kind.same_kind(&kind))
+    }
+}
+
+/// Item puts a status effect on whoever it is used on
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct AppliesStatus {
+    pub effect: StatusEffect,
+}
@@ -1,8 +1,9 @@
 use rltk::Point;
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use specs_derive::Component;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Viewshed {
     pub visible_tiles: Vec<Point>,
     pub range: i32,
@@ -18,3 +19,8 @@ impl Viewshed {
         }
     }
 }
+impl Default for Viewshed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
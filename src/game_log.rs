@@ -1,4 +1,6 @@
+use serde::{Deserialize, Serialize};
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct GameLog {
     pub entries: Vec<String>
 }
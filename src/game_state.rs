@@ -1,27 +1,140 @@
-use rltk::Point;
 use rltk::{GameState, Rltk, VirtualKeyCode};
+use rltk::{Point, RandomNumberGenerator};
 use specs::prelude::*;
-use std::cmp::{max, min};
+use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use crate::components::Viewshed;
-use crate::components::{CombatStats, PlayerChar};
-use crate::components::{GameplayName, Renderable};
-use crate::components::{MeleeAttackIntent, Position};
+use crate::camera::Camera;
+use crate::components::{
+    ranged_attack_of, AppliesStatus, AreaOfEffect, CombatStats, Companion, CompanionOrder,
+    CompanionTarget, Confusion, Consumable, DefenseBonus, Equippable, Equipped, Experience,
+    Faction, GameplayName, Healer, InBackpack, IncomingDamage, InflictsDamage, Initiative, Item,
+    KilledBy, LeftMover, MeleeAttackIntent, MeleePowerBonus, MonsterAI, MonsterChar, MoveIntent,
+    MyTurn, PlayerChar, Position, Ranged, RangedAttackIntent, RangedAttacker, RangedWeapon,
+    Reaction, Renderable, StatusEffects, TileBlocker, Viewshed, WantsToDrop, WantsToEquip,
+    WantsToPickUp, WantsToUnequip, WantsToUse, XpReward, ACTION_COST, PLAYER_FACTION,
+};
 use crate::game_log::GameLog;
-use crate::gui;
-use crate::level::{draw_tiles, Level};
-use crate::systems::{DamageSystem, MapIndexingSystem, MonsterAISystem};
-use crate::systems::{MeleeCombatSystem, VisibilitySystem};
+use crate::gui::{self, GameOverResult, ItemMenuResult, MenuResult, TargetingResult};
+use crate::gui::{DebugOverlay, MainMenuSelection, PauseMenuSelection};
+use crate::level::{draw_tiles, DistanceMap, Level, TileType};
+use crate::map_builders::{self, MapOptions};
+use crate::raws::Raws;
+use crate::save_load::{self, SerializationHelper, SerializeMe};
+use crate::seed::RunSeed;
+use crate::spawner;
+use crate::systems::{CompanionAISystem, DamageSystem, InitiativeSystem, MapIndexingSystem};
+use crate::systems::{DistanceMapSystem, MonsterAISystem, MovementSystem, UnhandledTurnSystem};
+use crate::systems::{ItemCollectionSystem, ItemDropSystem, ItemEquipSystem, ItemUseSystem};
+use crate::systems::{MeleeCombatSystem, RangedCombatSystem, StatusEffectSystem, VisibilitySystem};
 
 /// Current status of the game, used in tick to accomodate the turn-based nature of the gameplay
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum GameStatus {
     AwaitingInput,
     PreTurn,
     PlayerTurn,
+    /// Monsters that have a turn (MyTurn) are acting
     MonsterTurn,
+    /// Time passes until someone has enough energy to act
+    Ticking,
+    /// Inventory menu is open, picking an item to use
+    ShowInventory,
+    /// Inventory menu is open, picking an item to drop
+    ShowDropItem,
+    /// Picking which companion to give an order to
+    ShowCompanions,
+    /// Picking the order for `companion`, `selected` is under the cursor
+    ShowOrders {
+        companion: Entity,
+        selected: CompanionOrder,
+    },
+    /// Title screen, there may be no world to draw yet
+    MainMenu {
+        selected: MainMenuSelection,
+    },
+    /// Game is suspended, pause menu is drawn on top of the world
+    Paused {
+        selected: PauseMenuSelection,
+    },
+    /// Player has died, the run is over
+    GameOver,
+    /// Player took the stairs, the next level has to be generated
+    NextLevel,
+    /// Picking a tile within `range` for `purpose`, `cursor` is the tile picked so far.
+    /// `radius` is only there to preview the area of effect, 0 hits a single tile.
+    ShowTargeting {
+        range: i32,
+        radius: i32,
+        purpose: TargetingPurpose,
+        cursor: Point,
+    },
+}
+
+/// What the tile picked in targeting mode is going to be used for
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TargetingPurpose {
+    /// Shoot the player's ranged weapon at it
+    Shoot,
+    /// Use a Ranged item from the backpack on it
+    UseItem(Entity),
+    /// Send the companion after whoever stands there
+    CompanionAttack(Entity),
+}
+
+impl GameStatus {
+    /// Rets true if there is a run going on, which can be drawn, saved and simulated
+    pub fn is_in_run(&self) -> bool {
+        !matches!(self, GameStatus::MainMenu { .. } | GameStatus::GameOver)
+    }
+}
+
+/// Action the player takes on their turn, whether it comes from the keyboard or a script
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum PlayerCommand {
+    /// Step in given direction, attacking whatever stands there
+    Move {
+        dx: i32,
+        dy: i32,
+    },
+    /// Pass the turn
+    Wait,
+    /// Pick up the item lying under the player
+    PickUp,
+    /// Take the stairs down, if the player stands on them
+    Descend,
+    /// Use a carried item. `target` is needed by Ranged items only.
+    UseItem {
+        item: Entity,
+        target: Option<Point>,
+    },
+    DropItem(Entity),
+    /// Wear a carried item, taking off whatever is in its slot
+    Equip(Entity),
+    /// Take off a worn item, it stays in the backpack
+    Unequip(Entity),
+    /// Shoot the player's ranged weapon at given tile
+    Shoot {
+        target: Point,
+    },
+    /// Tell a companion what to do. `target` is needed by the Attack order only.
+    /// Shouting an order doesn't take a turn.
+    Order {
+        companion: Entity,
+        order: CompanionOrder,
+        target: Option<Entity>,
+    },
+}
+
+impl PlayerCommand {
+    /// Energy the command takes. Fiddling with the backpack is quicker than a step.
+    pub fn energy_cost(&self) -> i32 {
+        match self {
+            PlayerCommand::UseItem { .. } | PlayerCommand::DropItem(_) => ACTION_COST / 2,
+            _ => ACTION_COST,
+        }
+    }
 }
 
 pub struct State {
@@ -40,20 +153,181 @@ impl GameState for State {
 
             // Copy current game status
             let old_status = *self.ecs.fetch::<GameStatus>();
-            let new_status = match old_status {
-                GameStatus::PreTurn => {
-                    self.run_systems();
-                    GameStatus::AwaitingInput
+
+            // Save and quit, if the window is being closed
+            let mut close_requested = false;
+            rltk::INPUT.lock().for_each_message(|event| {
+                if event == rltk::BEvent::CloseRequested {
+                    close_requested = true;
+                }
+            });
+            if close_requested {
+                if old_status.is_in_run() {
+                    if let Err(err) = save_load::save_game(&mut self.ecs) {
+                        self.ecs
+                            .write_resource::<GameLog>()
+                            .log(format!("The game couldn't be saved: {}", err));
+                    }
                 }
+                ctx.quit();
+                return;
+            }
+
+            // Render the world first, so that menus can be drawn on top of it
+            if old_status.is_in_run() {
+                draw_world(&self.ecs, ctx);
+            }
+
+            let new_status = match old_status {
+                GameStatus::PreTurn
+                | GameStatus::PlayerTurn
+                | GameStatus::MonsterTurn
+                | GameStatus::Ticking
+                | GameStatus::NextLevel => self.advance_turn(old_status),
                 GameStatus::AwaitingInput => process_input(self, ctx),
-                GameStatus::PlayerTurn => {
-                    self.run_systems();
-                    GameStatus::MonsterTurn
+                GameStatus::ShowInventory => {
+                    match gui::show_inventory(&self.ecs, ctx, "Inventory") {
+                        (ItemMenuResult::Cancel, _) => GameStatus::AwaitingInput,
+                        (ItemMenuResult::Selected, Some(item)) => self.use_or_aim_item(item),
+                        _ => GameStatus::ShowInventory,
+                    }
                 }
-                GameStatus::MonsterTurn => {
-                    self.run_systems();
-                    GameStatus::AwaitingInput
+                GameStatus::ShowDropItem => {
+                    match gui::show_inventory(&self.ecs, ctx, "Drop which item?") {
+                        (ItemMenuResult::Cancel, _) => GameStatus::AwaitingInput,
+                        (ItemMenuResult::Selected, Some(item)) => {
+                            self.apply_command(PlayerCommand::DropItem(item))
+                        }
+                        _ => GameStatus::ShowDropItem,
+                    }
                 }
+                GameStatus::ShowCompanions => match gui::show_companions(&self.ecs, ctx) {
+                    (ItemMenuResult::Cancel, _) => GameStatus::AwaitingInput,
+                    (ItemMenuResult::Selected, Some(companion)) => GameStatus::ShowOrders {
+                        companion,
+                        selected: CompanionOrder::Follow,
+                    },
+                    _ => GameStatus::ShowCompanions,
+                },
+                GameStatus::ShowOrders {
+                    companion,
+                    selected,
+                } => match gui::order_menu(&self.ecs, ctx, companion, selected) {
+                    None => GameStatus::AwaitingInput,
+                    Some(MenuResult::NoSelection { selected }) => GameStatus::ShowOrders {
+                        companion,
+                        selected,
+                    },
+                    Some(MenuResult::Selected {
+                        selected: CompanionOrder::Attack,
+                    }) => start_ordering_attack(&self.ecs, companion),
+                    Some(MenuResult::Selected { selected }) => {
+                        self.apply_command(PlayerCommand::Order {
+                            companion,
+                            order: selected,
+                            target: None,
+                        })
+                    }
+                },
+                GameStatus::ShowTargeting {
+                    range,
+                    radius,
+                    purpose,
+                    cursor,
+                } => match gui::show_targeting(&self.ecs, ctx, range, radius, cursor) {
+                    TargetingResult::Cancel => GameStatus::AwaitingInput,
+                    TargetingResult::NoResponse { cursor } => GameStatus::ShowTargeting {
+                        range,
+                        radius,
+                        purpose,
+                        cursor,
+                    },
+                    TargetingResult::Selected { target } => match purpose {
+                        TargetingPurpose::Shoot => {
+                            self.apply_command(PlayerCommand::Shoot { target })
+                        }
+                        TargetingPurpose::UseItem(item) => {
+                            self.apply_command(PlayerCommand::UseItem {
+                                item,
+                                target: Some(target),
+                            })
+                        }
+                        TargetingPurpose::CompanionAttack(companion) => {
+                            match attackable_at(&self.ecs, target) {
+                                Some(victim) => self.apply_command(PlayerCommand::Order {
+                                    companion,
+                                    order: CompanionOrder::Attack,
+                                    target: Some(victim),
+                                }),
+                                None => {
+                                    self.ecs
+                                        .write_resource::<GameLog>()
+                                        .log("There is nobody there to attack.".to_string());
+                                    GameStatus::AwaitingInput
+                                }
+                            }
+                        }
+                    },
+                },
+                GameStatus::MainMenu { selected } => match gui::main_menu(&self.ecs, ctx, selected)
+                {
+                    MenuResult::NoSelection { selected } => GameStatus::MainMenu { selected },
+                    MenuResult::Selected { selected } => match selected {
+                        MainMenuSelection::NewGame => {
+                            self.new_game();
+                            GameStatus::PreTurn
+                        }
+                        MainMenuSelection::Continue => match save_load::load_game(&mut self.ecs) {
+                            Ok(()) => GameStatus::PreTurn,
+                            Err(err) => {
+                                // A broken save would fail the same way every time, so drop it
+                                save_load::delete_save();
+                                self.ecs.delete_all();
+                                let mut log = GameLog {
+                                    entries: Vec::new(),
+                                };
+                                log.log(format!(
+                                    "The save couldn't be loaded and was deleted: {}",
+                                    err
+                                ));
+                                self.ecs.insert(log);
+                                GameStatus::MainMenu {
+                                    selected: MainMenuSelection::NewGame,
+                                }
+                            }
+                        },
+                        MainMenuSelection::Quit => {
+                            ctx.quit();
+                            GameStatus::MainMenu { selected }
+                        }
+                    },
+                },
+                GameStatus::Paused { selected } => match gui::pause_menu(ctx, selected) {
+                    MenuResult::NoSelection { selected } => GameStatus::Paused { selected },
+                    MenuResult::Selected { selected } => match selected {
+                        PauseMenuSelection::Resume => GameStatus::AwaitingInput,
+                        PauseMenuSelection::SaveAndExit => {
+                            match save_load::save_game(&mut self.ecs) {
+                                Ok(()) => GameStatus::MainMenu {
+                                    selected: MainMenuSelection::Continue,
+                                },
+                                // Stay in the run rather than lose it
+                                Err(err) => {
+                                    self.ecs
+                                        .write_resource::<GameLog>()
+                                        .log(format!("The game couldn't be saved: {}", err));
+                                    GameStatus::AwaitingInput
+                                }
+                            }
+                        }
+                    },
+                },
+                GameStatus::GameOver => match gui::game_over(ctx) {
+                    GameOverResult::NoSelection => GameStatus::GameOver,
+                    GameOverResult::QuitToMenu => GameStatus::MainMenu {
+                        selected: MainMenuSelection::NewGame,
+                    },
+                },
             };
             // Write new status:
             {
@@ -61,23 +335,13 @@ impl GameState for State {
                 *status_writer = new_status;
             }
             // Get rid of dead entities
-            destroy_dead_entities(&mut self.ecs);
-
-            // Render map
-            draw_tiles(&mut self.ecs, ctx);
-
-            // Render entities
-            let positions = self.ecs.read_storage::<Position>();
-            let renderables = self.ecs.read_storage::<Renderable>();
-            let level = self.ecs.fetch::<Level>();
-
-            for (pos, ren) in (&positions, &renderables).join() {
-                if level.is_tile_visible(level.xy_idx(pos.x, pos.y)) {
-                    ctx.set(pos.x, pos.y, ren.fg, ren.bg, ren.glyph);
+            if new_status.is_in_run() {
+                destroy_dead_entities(&mut self.ecs);
+                // Permadeath: there is nothing to continue anymore
+                if *self.ecs.fetch::<GameStatus>() == GameStatus::GameOver {
+                    save_load::delete_save();
                 }
             }
-
-            gui::draw_ui(&self.ecs, ctx);
         }
 
         let tick_time = tick_start_time.elapsed();
@@ -88,20 +352,358 @@ impl GameState for State {
     }
 }
 impl State {
+    /// Creates the world with every component registered, sitting in the main menu
+    pub fn new(raws: Raws) -> Self {
+        let mut ecs = World::new();
+        register_components(&mut ecs);
+
+        // Insert monster and item templates
+        ecs.insert(raws);
+
+        // Insert globally-available turn status, every run starts from the main menu
+        ecs.insert(GameStatus::MainMenu {
+            selected: MainMenuSelection::NewGame,
+        });
+
+        // Developer overlays are off until toggled
+        ecs.insert(DebugOverlay::default());
+
+        // Builder and level size asked for on the command line, if any
+        ecs.insert(MapOptions::from_args_or_env());
+
+        // Insert marker allocator, tagging the entities that go into the save file
+        ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+
+        State { ecs }
+    }
+
+    /// Tears down whatever is left of the previous run and builds a fresh one
+    pub fn new_game(&mut self) {
+        self.new_game_with_seed(RunSeed::from_args_or_env());
+    }
+
+    /// Same as new_game, but with a given seed instead of the one from the CLI args
+    pub fn new_game_with_seed(&mut self, seed: RunSeed) {
+        self.ecs.delete_all();
+
+        // Every bit of randomness in the run comes from this seed
+        self.ecs.insert(seed.rng());
+        self.ecs.insert(seed);
+
+        // Insert game log
+        let mut log = GameLog {
+            entries: vec!["Welcome and good luck!".to_string()],
+        };
+        log.log(format!("Run seed: {}", seed.0));
+        self.ecs.insert(log);
+
+        let pl_spawn_pos = self.generate_level(1);
+        // Create player:
+        let player_ent = spawner::spawn_player(&mut self.ecs, pl_spawn_pos);
+        self.ecs.insert(player_ent);
+    }
+
+    /// Creates the level at given depth along with its content. Rets player starting loc.
+    fn generate_level(&mut self, depth: i32) -> (i32, i32) {
+        // Create map:
+        let builder = {
+            let options = self.ecs.fetch::<MapOptions>();
+            let mut rng = self.ecs.write_resource::<RandomNumberGenerator>();
+            let mut builder = map_builders::new_builder(depth, &options, &mut rng);
+            builder.build_map(&mut rng);
+            builder
+        };
+
+        // Obtian player starting loc, write it down as a resource for monsters to use
+        let pl_spawn_pos = builder.get_player_start();
+        self.ecs.insert(Point::new(pl_spawn_pos.0, pl_spawn_pos.1));
+        // Spawner needs the new level's dimensions, so it goes in first
+        let mut level = builder.get_level();
+        level.index_walls();
+        self.ecs.insert(level);
+
+        // Spawn monsters:
+        for region in builder.get_spawn_regions().iter() {
+            spawner::spawn_region(&mut self.ecs, region, depth);
+        }
+
+        pl_spawn_pos
+    }
+
+    /// Replaces the current level with a deeper one. Only the player, their backpack and the
+    /// companions that weren't told to stay are kept.
+    fn goto_next_level(&mut self) {
+        let player_ent = *self.ecs.fetch::<Entity>();
+        let to_delete: Vec<Entity> = {
+            let entities = self.ecs.entities();
+            let backpack = self.ecs.read_storage::<InBackpack>();
+            let companions = self.ecs.read_storage::<Companion>();
+            entities
+                .join()
+                .filter(|&ent| {
+                    let is_players_item = backpack
+                        .get(ent)
+                        .is_some_and(|carried| carried.owner == player_ent);
+                    // Whoever was told to stay is left behind
+                    let comes_along = companions
+                        .get(ent)
+                        .is_some_and(|companion| companion.order != CompanionOrder::Stay);
+                    ent != player_ent && !is_players_item && !comes_along
+                })
+                .collect()
+        };
+        self.ecs
+            .delete_entities(&to_delete)
+            .expect("Should be able to delete the entities of the previous level");
+
+        let depth = self.ecs.fetch::<Level>().depth + 1;
+        let pl_spawn_pos = self.generate_level(depth);
+
+        // Place the player at the start of the new level
+        {
+            let mut positions = self.ecs.write_storage::<Position>();
+            if let Some(pos) = positions.get_mut(player_ent) {
+                pos.x = pl_spawn_pos.0;
+                pos.y = pl_spawn_pos.1;
+            }
+            let mut viewsheds = self.ecs.write_storage::<Viewshed>();
+            if let Some(vs) = viewsheds.get_mut(player_ent) {
+                vs.is_dirty = true;
+            }
+        }
+
+        self.place_followers(pl_spawn_pos);
+
+        self.ecs
+            .write_resource::<GameLog>()
+            .log(format!("You descend to depth {}.", depth));
+    }
+
+    /// Puts the companions that came along on the free floor closest to the player's start.
+    /// Whatever they were attacking stayed upstairs, so they go back to following.
+    fn place_followers(&mut self, around: (i32, i32)) {
+        let entities = self.ecs.entities();
+        let mut companions = self.ecs.write_storage::<Companion>();
+        let mut targets = self.ecs.write_storage::<CompanionTarget>();
+        let mut positions = self.ecs.write_storage::<Position>();
+        let mut viewsheds = self.ecs.write_storage::<Viewshed>();
+        let blockers = self.ecs.read_storage::<TileBlocker>();
+        let level = self.ecs.fetch::<Level>();
+
+        let followers: Vec<Entity> = (&entities, &companions)
+            .join()
+            .map(|(ent, _)| ent)
+            .collect();
+        let taken: Vec<usize> = (&entities, &positions, &blockers)
+            .join()
+            .filter(|(ent, _, _)| !followers.contains(ent))
+            .map(|(_, pos, _)| level.xy_idx(pos.x, pos.y))
+            .collect();
+
+        // Closest by walking distance, so that nobody lands behind a wall
+        let to_start = DistanceMap::new(&level, &[level.xy_idx(around.0, around.1)]);
+        let mut free_tiles: Vec<usize> = (0..level.tiles.len())
+            .filter(|&idx| {
+                to_start.value(idx) != DistanceMap::UNREACHABLE
+                    && level.tiles[idx] != TileType::Wall
+                    && !taken.contains(&idx)
+            })
+            .collect();
+        free_tiles.sort_by(|&a, &b| to_start.value(a).total_cmp(&to_start.value(b)));
+
+        for (ent, idx) in followers.into_iter().zip(free_tiles) {
+            if let Some(pos) = positions.get_mut(ent) {
+                (pos.x, pos.y) = level.idx_xy(idx);
+            }
+            if let Some(vs) = viewsheds.get_mut(ent) {
+                vs.is_dirty = true;
+            }
+            if let Some(companion) = companions.get_mut(ent) {
+                companion.order = CompanionOrder::Follow;
+            }
+            targets.remove(ent);
+        }
+    }
+
+    /// Queues the player's action. Rets the status the turn machine should continue with.
+    pub fn apply_command(&mut self, command: PlayerCommand) -> GameStatus {
+        match command {
+            PlayerCommand::Move { dx, dy } => move_player(dx, dy, &mut self.ecs),
+            PlayerCommand::Wait => {}
+            PlayerCommand::PickUp => pick_up_item(&mut self.ecs),
+            PlayerCommand::Descend => {
+                if try_descend(&mut self.ecs) {
+                    self.end_player_turn(command.energy_cost());
+                    return GameStatus::NextLevel;
+                }
+                return GameStatus::AwaitingInput;
+            }
+            PlayerCommand::UseItem { item, target } => {
+                let player_ent = *self.ecs.fetch::<Entity>();
+                self.ecs
+                    .write_storage::<WantsToUse>()
+                    .insert(player_ent, WantsToUse { item, target })
+                    .expect("Should be able to insert use intent to the player entity");
+            }
+            PlayerCommand::DropItem(item) => {
+                let player_ent = *self.ecs.fetch::<Entity>();
+                self.ecs
+                    .write_storage::<WantsToDrop>()
+                    .insert(player_ent, WantsToDrop { item })
+                    .expect("Should be able to insert drop intent to the player entity");
+            }
+            PlayerCommand::Equip(item) => {
+                let player_ent = *self.ecs.fetch::<Entity>();
+                self.ecs
+                    .write_storage::<WantsToEquip>()
+                    .insert(player_ent, WantsToEquip { item })
+                    .expect("Should be able to insert equip intent to the player entity");
+            }
+            PlayerCommand::Unequip(item) => {
+                let player_ent = *self.ecs.fetch::<Entity>();
+                self.ecs
+                    .write_storage::<WantsToUnequip>()
+                    .insert(player_ent, WantsToUnequip { item })
+                    .expect("Should be able to insert unequip intent to the player entity");
+            }
+            PlayerCommand::Shoot { target } => {
+                let player_ent = *self.ecs.fetch::<Entity>();
+                self.ecs
+                    .write_storage::<RangedAttackIntent>()
+                    .insert(player_ent, RangedAttackIntent { target })
+                    .expect("Should be able to insert ranged attack intent to the player entity");
+            }
+            PlayerCommand::Order {
+                companion,
+                order,
+                target,
+            } => {
+                give_order(&mut self.ecs, companion, order, target);
+                return GameStatus::AwaitingInput;
+            }
+        }
+        self.end_player_turn(command.energy_cost());
+        GameStatus::PlayerTurn
+    }
+
+    /// Ranged items need a target first, worn ones are put on or taken off,
+    /// everything else is used right away
+    fn use_or_aim_item(&mut self, item: Entity) -> GameStatus {
+        if self.ecs.read_storage::<Equippable>().contains(item) {
+            let is_worn = self.ecs.read_storage::<Equipped>().contains(item);
+            return if is_worn {
+                self.apply_command(PlayerCommand::Unequip(item))
+            } else {
+                self.apply_command(PlayerCommand::Equip(item))
+            };
+        }
+
+        let range = self
+            .ecs
+            .read_storage::<Ranged>()
+            .get(item)
+            .map(|ranged| ranged.range);
+        match range {
+            None => self.apply_command(PlayerCommand::UseItem { item, target: None }),
+            Some(range) => {
+                let radius = self
+                    .ecs
+                    .read_storage::<AreaOfEffect>()
+                    .get(item)
+                    .map_or(0, |aoe| aoe.radius);
+                start_targeting(&self.ecs, range, radius, TargetingPurpose::UseItem(item))
+            }
+        }
+    }
+
+    fn end_player_turn(&mut self, cost: i32) {
+        let player_ent = *self.ecs.fetch::<Entity>();
+        if let Some(exp) = self.ecs.write_storage::<Experience>().get_mut(player_ent) {
+            exp.level_up_flash = i32::max(0, exp.level_up_flash - 1);
+        }
+        if let Some(initiative) = self.ecs.write_storage::<Initiative>().get_mut(player_ent) {
+            initiative.spend(cost);
+        }
+        self.ecs.write_storage::<MyTurn>().remove(player_ent);
+    }
+
+    /// Steps the turn machine through the statuses that need neither input nor rendering.
+    /// Any other status is returned unchanged.
+    pub fn advance_turn(&mut self, status: GameStatus) -> GameStatus {
+        match status {
+            GameStatus::PreTurn | GameStatus::PlayerTurn | GameStatus::MonsterTurn => {
+                self.run_systems();
+                GameStatus::Ticking
+            }
+            GameStatus::Ticking => {
+                // Actors still holding a turn from the last tick had their effects ticked already
+                let were_turns_pending = !self.ecs.read_storage::<MyTurn>().is_empty();
+                let mut initiative = InitiativeSystem {};
+                initiative.run_now(&self.ecs);
+                if !were_turns_pending {
+                    let mut status_effects = StatusEffectSystem {};
+                    status_effects.run_now(&self.ecs);
+                    let mut dmg_system = DamageSystem {};
+                    dmg_system.run_now(&self.ecs);
+                    self.ecs.maintain();
+                }
+
+                let player_ent = *self.ecs.fetch::<Entity>();
+                if self.ecs.read_storage::<MyTurn>().contains(player_ent) {
+                    GameStatus::AwaitingInput
+                } else {
+                    GameStatus::MonsterTurn
+                }
+            }
+            GameStatus::NextLevel => {
+                self.goto_next_level();
+                GameStatus::PreTurn
+            }
+            _ => status,
+        }
+    }
+
     fn run_systems(&mut self) {
         puffin::profile_function!();
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
 
+        let mut distance_maps = DistanceMapSystem {};
+        distance_maps.run_now(&self.ecs);
+
         let mut monster_ai = MonsterAISystem {};
         monster_ai.run_now(&self.ecs);
 
+        let mut companion_ai = CompanionAISystem {};
+        companion_ai.run_now(&self.ecs);
+
+        let mut unhandled_turns = UnhandledTurnSystem {};
+        unhandled_turns.run_now(&self.ecs);
+
+        let mut movement = MovementSystem {};
+        movement.run_now(&self.ecs);
+
         let mut map_indexer = MapIndexingSystem {};
         map_indexer.run_now(&self.ecs);
 
         let mut melee_combat_system = MeleeCombatSystem {};
         melee_combat_system.run_now(&self.ecs);
 
+        let mut ranged_combat_system = RangedCombatSystem {};
+        ranged_combat_system.run_now(&self.ecs);
+
+        let mut item_collection_system = ItemCollectionSystem {};
+        item_collection_system.run_now(&self.ecs);
+
+        let mut item_use_system = ItemUseSystem {};
+        item_use_system.run_now(&self.ecs);
+
+        let mut item_equip_system = ItemEquipSystem {};
+        item_equip_system.run_now(&self.ecs);
+
+        let mut item_drop_system = ItemDropSystem {};
+        item_drop_system.run_now(&self.ecs);
+
         let mut dmg_system = DamageSystem {};
         dmg_system.run_now(&self.ecs);
 
@@ -109,23 +711,111 @@ impl State {
     }
 }
 
+fn register_components(ecs: &mut World) {
+    ecs.register::<Position>();
+    ecs.register::<Renderable>();
+    ecs.register::<LeftMover>();
+    ecs.register::<PlayerChar>();
+    ecs.register::<Viewshed>();
+    ecs.register::<MonsterChar>();
+    ecs.register::<GameplayName>();
+    ecs.register::<TileBlocker>();
+    ecs.register::<CombatStats>();
+    ecs.register::<MeleeAttackIntent>();
+    ecs.register::<MoveIntent>();
+    ecs.register::<IncomingDamage>();
+    ecs.register::<Item>();
+    ecs.register::<Healer>();
+    ecs.register::<Consumable>();
+    ecs.register::<InBackpack>();
+    ecs.register::<WantsToPickUp>();
+    ecs.register::<WantsToDrop>();
+    ecs.register::<WantsToUse>();
+    ecs.register::<Initiative>();
+    ecs.register::<MyTurn>();
+    ecs.register::<RangedAttacker>();
+    ecs.register::<RangedAttackIntent>();
+    ecs.register::<InflictsDamage>();
+    ecs.register::<Ranged>();
+    ecs.register::<AreaOfEffect>();
+    ecs.register::<Confusion>();
+    ecs.register::<StatusEffects>();
+    ecs.register::<AppliesStatus>();
+    ecs.register::<Equippable>();
+    ecs.register::<Equipped>();
+    ecs.register::<MeleePowerBonus>();
+    ecs.register::<DefenseBonus>();
+    ecs.register::<RangedWeapon>();
+    ecs.register::<WantsToEquip>();
+    ecs.register::<WantsToUnequip>();
+    ecs.register::<Experience>();
+    ecs.register::<XpReward>();
+    ecs.register::<KilledBy>();
+    ecs.register::<MonsterAI>();
+    ecs.register::<Faction>();
+    ecs.register::<Companion>();
+    ecs.register::<CompanionTarget>();
+    ecs.register::<SimpleMarker<SerializeMe>>();
+    ecs.register::<SerializationHelper>();
+}
+
+fn draw_world(ecs: &World, ctx: &mut Rltk) {
+    // Render map
+    draw_tiles(ecs, ctx);
+
+    // Render entities
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let level = ecs.fetch::<Level>();
+    let camera = Camera::new(ecs);
+
+    for (pos, ren) in (&positions, &renderables).join() {
+        if !level.is_tile_visible(level.xy_idx(pos.x, pos.y)) {
+            continue;
+        }
+        if let Some((screen_x, screen_y)) = camera.world_to_screen(pos.x, pos.y) {
+            ctx.set(screen_x, screen_y, ren.fg, ren.bg, ren.glyph);
+        }
+    }
+
+    gui::draw_ui(ecs, ctx);
+}
+
+/// Translates the pressed key into a player command. Menu keys switch the status directly.
 fn process_input(gs: &mut State, ctx: &mut Rltk) -> GameStatus {
-    match ctx.key {
+    let command = match ctx.key {
         None => return GameStatus::AwaitingInput,
         Some(key) => match key {
-            VirtualKeyCode::Left | VirtualKeyCode::A => move_player(-1, 0, &mut gs.ecs),
-            VirtualKeyCode::Right | VirtualKeyCode::D => move_player(1, 0, &mut gs.ecs),
-            VirtualKeyCode::Up | VirtualKeyCode::W => move_player(0, -1, &mut gs.ecs),
-            VirtualKeyCode::Down | VirtualKeyCode::S => move_player(0, 1, &mut gs.ecs),
-            VirtualKeyCode::Q => move_player(-1, -1, &mut gs.ecs),
-            VirtualKeyCode::E => move_player(1, -1, &mut gs.ecs),
-            VirtualKeyCode::C => move_player(1, 1, &mut gs.ecs),
-            VirtualKeyCode::Z => move_player(-1, 1, &mut gs.ecs),
-
-            _ => return GameStatus::PlayerTurn,
+            VirtualKeyCode::Left | VirtualKeyCode::A => PlayerCommand::Move { dx: -1, dy: 0 },
+            VirtualKeyCode::Right | VirtualKeyCode::D => PlayerCommand::Move { dx: 1, dy: 0 },
+            VirtualKeyCode::Up | VirtualKeyCode::W => PlayerCommand::Move { dx: 0, dy: -1 },
+            VirtualKeyCode::Down | VirtualKeyCode::S => PlayerCommand::Move { dx: 0, dy: 1 },
+            VirtualKeyCode::Q => PlayerCommand::Move { dx: -1, dy: -1 },
+            VirtualKeyCode::E => PlayerCommand::Move { dx: 1, dy: -1 },
+            VirtualKeyCode::C => PlayerCommand::Move { dx: 1, dy: 1 },
+            VirtualKeyCode::Z => PlayerCommand::Move { dx: -1, dy: 1 },
+
+            VirtualKeyCode::G => PlayerCommand::PickUp,
+            VirtualKeyCode::Period => PlayerCommand::Descend,
+            VirtualKeyCode::I => return GameStatus::ShowInventory,
+            VirtualKeyCode::X => return GameStatus::ShowDropItem,
+            VirtualKeyCode::F => return start_shooting(&mut gs.ecs),
+            VirtualKeyCode::O => return start_ordering(&mut gs.ecs),
+            VirtualKeyCode::F3 => {
+                let mut overlay = gs.ecs.write_resource::<DebugOverlay>();
+                overlay.show_ai = !overlay.show_ai;
+                return GameStatus::AwaitingInput;
+            }
+            VirtualKeyCode::Escape => {
+                return GameStatus::Paused {
+                    selected: PauseMenuSelection::Resume,
+                }
+            }
+
+            _ => PlayerCommand::Wait,
         },
-    }
-    GameStatus::PlayerTurn
+    };
+    gs.apply_command(command)
 }
 
 fn move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
@@ -135,11 +825,29 @@ fn move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let combat_stats = ecs.read_storage::<CombatStats>();
     let entities = ecs.entities();
     let mut melee_attackers = ecs.write_storage::<MeleeAttackIntent>();
+    let companions = ecs.read_storage::<Companion>();
     let level = ecs.fetch::<Level>();
 
+    // Companion in the way and the tile it gets in exchange
+    let mut swapped: Option<(Entity, (i32, i32))> = None;
     for (ent, _, pos, vs) in (&entities, &mut players, &mut positions, &mut viewsheds).join() {
         let target_idx = level.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
+        // Companions make way by swapping places with the player
+        if let Some(&ally) = level.tile_content[target_idx]
+            .iter()
+            .find(|&&other| companions.contains(other))
+        {
+            swapped = Some((ally, (pos.x, pos.y)));
+            pos.x += delta_x;
+            pos.y += delta_y;
+            vs.is_dirty = true;
+            let mut player_pos_storage = ecs.write_resource::<Point>();
+            player_pos_storage.x = pos.x;
+            player_pos_storage.y = pos.y;
+            break;
+        }
+
         // Before moving - let's see if we attack anything:
         for target in level.tile_content[target_idx].iter() {
             match combat_stats.get(*target) {
@@ -155,8 +863,8 @@ fn move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
         }
 
         if !level.is_tile_blocked(target_idx) {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+            pos.x = (pos.x + delta_x).clamp(0, level.width - 1);
+            pos.y = (pos.y + delta_y).clamp(0, level.height - 1);
 
             // Notify the viewshed that it's dirty
             vs.is_dirty = true;
@@ -166,26 +874,241 @@ fn move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             player_pos_storage.y = pos.y;
         }
     }
+
+    if let Some((ally, (x, y))) = swapped {
+        if let Some(pos) = positions.get_mut(ally) {
+            (pos.x, pos.y) = (x, y);
+        }
+        if let Some(vs) = viewsheds.get_mut(ally) {
+            vs.is_dirty = true;
+        }
+    }
+}
+
+/// Queues a pickup of whatever item lies under the player
+fn pick_up_item(ecs: &mut World) {
+    let player_ent = *ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+    let mut logger = ecs.write_resource::<GameLog>();
+
+    let player_pos = match positions.get(player_ent) {
+        Some(pos) => pos,
+        None => return,
+    };
+
+    let target_item = (&entities, &items, &positions)
+        .join()
+        .find(|(_, _, pos)| pos.x == player_pos.x && pos.y == player_pos.y)
+        .map(|(item_ent, _, _)| item_ent);
+
+    match target_item {
+        None => logger.log("There is nothing here to pick up.".to_string()),
+        Some(item) => {
+            let mut pickups = ecs.write_storage::<WantsToPickUp>();
+            pickups
+                .insert(
+                    player_ent,
+                    WantsToPickUp {
+                        collected_by: player_ent,
+                        item,
+                    },
+                )
+                .expect("Should be able to insert pickup intent to the player entity");
+        }
+    }
+}
+
+/// Enters targeting mode for the player's ranged weapon
+fn start_shooting(ecs: &mut World) -> GameStatus {
+    let player_ent = *ecs.fetch::<Entity>();
+    let weapon = ranged_attack_of(
+        player_ent,
+        &ecs.read_storage::<RangedAttacker>(),
+        &ecs.read_storage::<Equipped>(),
+        &ecs.read_storage::<RangedWeapon>(),
+    );
+    let range = match weapon {
+        Some(weapon) => weapon.range,
+        None => {
+            ecs.write_resource::<GameLog>()
+                .log("You have nothing to shoot with.".to_string());
+            return GameStatus::AwaitingInput;
+        }
+    };
+
+    start_targeting(ecs, range, 0, TargetingPurpose::Shoot)
+}
+
+/// Targeting mode with the nearest visible target preselected, or the player if there's none
+fn start_targeting(ecs: &World, range: i32, radius: i32, purpose: TargetingPurpose) -> GameStatus {
+    let cursor = gui::targets_in_range(ecs, range)
+        .first()
+        .copied()
+        .unwrap_or(*ecs.fetch::<Point>());
+    GameStatus::ShowTargeting {
+        range,
+        radius,
+        purpose,
+        cursor,
+    }
+}
+
+/// Companion menu, unless there's nobody to give orders to
+fn start_ordering(ecs: &mut World) -> GameStatus {
+    if orderable_companions(ecs).is_empty() {
+        ecs.write_resource::<GameLog>()
+            .log("You have no companions to give orders to.".to_string());
+        return GameStatus::AwaitingInput;
+    }
+    GameStatus::ShowCompanions
+}
+
+/// Targeting mode for an Attack order, reaching as far as the player sees
+fn start_ordering_attack(ecs: &World, companion: Entity) -> GameStatus {
+    let player_ent = *ecs.fetch::<Entity>();
+    let range = ecs
+        .read_storage::<Viewshed>()
+        .get(player_ent)
+        .map_or(0, |vs| vs.range);
+    start_targeting(ecs, range, 0, TargetingPurpose::CompanionAttack(companion))
+}
+
+/// Whoever the companion could be sent after at the tile. The player and their friends don't count.
+fn attackable_at(ecs: &World, target: Point) -> Option<Entity> {
+    let level = ecs.fetch::<Level>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    level.tile_content[level.xy_idx(target.x, target.y)]
+        .iter()
+        .find(|&&ent| combat_stats.contains(ent) && !is_players_friend(ecs, ent))
+        .copied()
+}
+
+/// Rets true for the player, their companions and anyone else whose faction the player's is friendly to
+fn is_players_friend(ecs: &World, ent: Entity) -> bool {
+    let raws = ecs.fetch::<Raws>();
+    ent == *ecs.fetch::<Entity>()
+        || ecs
+            .read_storage::<Faction>()
+            .get(ent)
+            .is_some_and(|faction| {
+                raws.reaction(PLAYER_FACTION, &faction.name) == Reaction::Friendly
+            })
+}
+
+/// Companions the player can give orders to: the recruited ones wherever they are,
+/// and the rest only while the player sees them
+pub fn orderable_companions(ecs: &World) -> Vec<Entity> {
+    let player_ent = *ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let companions = ecs.read_storage::<Companion>();
+    let positions = ecs.read_storage::<Position>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let player_vs = viewsheds.get(player_ent);
+
+    (&entities, &companions)
+        .join()
+        .filter(|(ent, companion)| {
+            companion.recruited
+                || positions.get(*ent).is_some_and(|pos| {
+                    player_vs.is_some_and(|vs| vs.visible_tiles.contains(&Point::new(pos.x, pos.y)))
+                })
+        })
+        .map(|(ent, _)| ent)
+        .collect()
+}
+
+/// Sets the companion's order, recruiting it if it wasn't yet. An Attack order without a target,
+/// or against one of the player's friends, is ignored, as is any order to a companion out of reach.
+fn give_order(ecs: &mut World, companion: Entity, order: CompanionOrder, target: Option<Entity>) {
+    if !orderable_companions(ecs).contains(&companion) {
+        return;
+    }
+    if order == CompanionOrder::Attack
+        && target.is_some_and(|target| is_players_friend(ecs, target))
+    {
+        return;
+    }
+    let mut companions = ecs.write_storage::<Companion>();
+    let mut targets = ecs.write_storage::<CompanionTarget>();
+    let names = ecs.read_storage::<GameplayName>();
+    let mut logger = ecs.write_resource::<GameLog>();
+
+    let ally = match companions.get_mut(companion) {
+        Some(ally) => ally,
+        None => return,
+    };
+    let name = names
+        .get(companion)
+        .map_or("companion", |name| name.name.as_str());
+    match (order, target) {
+        (CompanionOrder::Attack, Some(target)) => {
+            targets
+                .insert(companion, CompanionTarget { target })
+                .expect("Should be able to insert the companion's target");
+            let target_name = names.get(target).map_or("it", |name| name.name.as_str());
+            logger.log(format!(
+                "You tell the {} to attack the {}.",
+                name, target_name
+            ));
+        }
+        (CompanionOrder::Attack, None) => return,
+        (CompanionOrder::Follow, _) => {
+            targets.remove(companion);
+            logger.log(format!("You tell the {} to follow you.", name));
+        }
+        (CompanionOrder::Stay, _) => {
+            targets.remove(companion);
+            logger.log(format!("You tell the {} to stay.", name));
+        }
+    }
+    ally.order = order;
+    ally.recruited = true;
+}
+
+/// Rets true if the player stands on the stairs down
+fn try_descend(ecs: &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let level = ecs.fetch::<Level>();
+    let idx = level.xy_idx(player_pos.x, player_pos.y);
+
+    if level.tiles[idx] == TileType::DownStairs {
+        true
+    } else {
+        ecs.write_resource::<GameLog>()
+            .log("There is no way down from here.".to_string());
+        false
+    }
 }
 
-fn destroy_dead_entities(ecs: &mut World) {
+/// Deletes everything that ran out of hp, crediting their killers with xp.
+/// If the player is dead, the run is over.
+pub fn destroy_dead_entities(ecs: &mut World) {
     let mut dead: Vec<Entity> = Vec::new();
+    let mut rewards: Vec<(Entity, i32)> = Vec::new();
+    let mut is_player_dead = false;
 
     {
         let combat_stats = ecs.read_storage::<CombatStats>();
         let player_chars = ecs.read_storage::<PlayerChar>();
         let entities = ecs.entities();
         let names = ecs.read_storage::<GameplayName>();
+        let killed_by = ecs.read_storage::<KilledBy>();
+        let xp_rewards = ecs.read_storage::<XpReward>();
         let mut logger = ecs.write_resource::<GameLog>();
         for (ent, stats, name) in (&entities, &combat_stats, &names).join() {
             if stats.hp < 1 {
                 let is_player = player_chars.get(ent).is_some();
                 if is_player {
-                    // TODO: disable player controls if dead.
+                    is_player_dead = true;
                     logger.log("You are dead. Not a big surprise!".to_string());
                 } else {
                     dead.push(ent);
                     logger.log(format!("{} dies.", name.name));
+                    if let (Some(kill), Some(reward)) = (killed_by.get(ent), xp_rewards.get(ent)) {
+                        rewards.push((kill.killer, reward.xp));
+                    }
                 }
             }
         }
@@ -193,4 +1116,41 @@ fn destroy_dead_entities(ecs: &mut World) {
 
     ecs.delete_entities(&dead)
         .expect("Should be able to destroy dead bodies from the world");
+    award_xp(ecs, &rewards);
+
+    if is_player_dead {
+        *ecs.write_resource::<GameStatus>() = GameStatus::GameOver;
+    }
+}
+
+/// Hands out xp for kills, leveling up whoever reaches the next threshold
+fn award_xp(ecs: &mut World, rewards: &[(Entity, i32)]) {
+    let player_ent = *ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<GameplayName>();
+    let mut experiences = ecs.write_storage::<Experience>();
+    let mut combat_stats = ecs.write_storage::<CombatStats>();
+    let mut logger = ecs.write_resource::<GameLog>();
+
+    for (killer, xp) in rewards.iter() {
+        let (exp, stats) = match (experiences.get_mut(*killer), combat_stats.get_mut(*killer)) {
+            (Some(exp), Some(stats)) => (exp, stats),
+            // Killer died meanwhile or never levels up
+            _ => continue,
+        };
+        let levels_gained = exp.gain(*xp, stats);
+
+        if *killer == player_ent {
+            logger.log(format!("You gain {} xp.", xp));
+            if levels_gained > 0 {
+                logger.log(format!(
+                    "Welcome to level {}! You feel stronger.",
+                    exp.level
+                ));
+            }
+        } else if levels_gained > 0 {
+            if let Some(name) = names.get(*killer) {
+                logger.log(format!("{} grows stronger.", name.name));
+            }
+        }
+    }
 }
@@ -1,44 +1,188 @@
-use rltk::{RGB, Rltk, Point };
+use rltk::{RGB, Rltk, Point, VirtualKeyCode };
 use specs::prelude::*;
 
-use crate::{components::{CombatStats, PlayerChar, GameplayName, Position}, game_log::GameLog, level::Level};
+use crate::{camera::Camera, components::{xp_for_level, AiState, CombatStats, Companion, CompanionOrder, Equipped, Experience, PlayerChar, GameplayName, InBackpack, MonsterAI, Position, StatusEffects, StatusKind, Viewshed}, game_log::GameLog, game_state::orderable_companions, level::Level, save_load, seed::RunSeed};
+
+/// Outcome of a modal letter-indexed menu (items, companions) for the current frame
+#[derive(PartialEq, Copy, Clone)]
+pub enum ItemMenuResult {
+    /// Menu was closed without choosing anything
+    Cancel,
+    /// Menu is still open, waiting for a key
+    NoResponse,
+    /// An entry was chosen, its entity is returned alongside
+    Selected,
+}
+
+/// Outcome of a vertical option menu (main menu, pause menu) for the current frame
+#[derive(PartialEq, Copy, Clone)]
+pub enum MenuResult<T> {
+    /// Cursor is on `selected`, but nothing was confirmed yet
+    NoSelection { selected: T },
+    /// `selected` was confirmed with Enter
+    Selected { selected: T },
+}
+
+/// Outcome of the targeting mode for the current frame
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TargetingResult {
+    /// Targeting was abandoned
+    Cancel,
+    /// Still picking, `cursor` is where the keyboard selection ended up this frame
+    NoResponse { cursor: Point },
+    /// `target` was confirmed with Enter or a click
+    Selected { target: Point },
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum MainMenuSelection {
+    NewGame,
+    Continue,
+    Quit,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum PauseMenuSelection {
+    Resume,
+    SaveAndExit,
+}
+
+/// Developer views drawn over the world, inserted as a resource
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct DebugOverlay {
+    /// Shows each monster's AI state and where it last saw the player. Toggled with F3.
+    pub show_ai: bool,
+}
+
+/// Letter-indexed menus run out of letters after 'z', anything past that isn't listed
+pub const MAX_MENU_ENTRIES: usize = 26;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum GameOverResult {
+    NoSelection,
+    QuitToMenu,
+}
 
 pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     ctx.draw_box(0, 43, 79, 6, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
 
+    draw_depth(ecs, ctx);
     draw_player_hp(ecs, ctx);
+    draw_status_icons(ecs, ctx);
     draw_game_log(ecs, ctx);
+    draw_seed(ecs, ctx);
+    if ecs.fetch::<DebugOverlay>().show_ai {
+        draw_ai_overlay(ecs, ctx);
+    }
     draw_mouse(ecs, ctx);
     draw_tooltips(ecs, ctx);
 }
 
+fn draw_depth(ecs: &World, ctx: &mut Rltk) {
+    let level = ecs.fetch::<Level>();
+    let depth = format!("Depth: {}", level.depth);
+    ctx.print_color(2, 43, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), depth);
+}
+
+/// Hp text and bar, followed by the character level and xp towards the next one
 fn draw_player_hp(ecs: &World, ctx: &mut Rltk) {
     let combat_stats = ecs.read_storage::<CombatStats>();
+    let experiences = ecs.read_storage::<Experience>();
     let players = ecs.read_storage::<PlayerChar>();
 
-    for (_, stats) in (&players, &combat_stats).join() {
+    for (_, stats, exp) in (&players, &combat_stats, &experiences).join() {
         let health = format!("HP {}/{}", stats.hp, stats.max_hp);
         ctx.print_color(12, 43, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), health);
 
-        ctx.draw_bar_horizontal(28, 43, 51, stats.hp, stats.max_hp, RGB::named(rltk::RED), RGB::named(rltk::BLACK));
+        ctx.draw_bar_horizontal(28, 43, 28, stats.hp, stats.max_hp, RGB::named(rltk::RED), RGB::named(rltk::BLACK));
+
+        // Flashes for a few turns after a level up
+        if exp.level_up_flash > 0 {
+            ctx.print_color(58, 43, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), format!("LEVEL UP! Lvl {}", exp.level));
+        } else {
+            let level = format!("Lvl {} XP {}/{}", exp.level, exp.xp, xp_for_level(exp.level + 1));
+            ctx.print_color(58, 43, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), level);
+        }
+    }
+}
+
+/// One colored glyph per effect on the player, squeezed between the hp text and the hp bar
+fn draw_status_icons(ecs: &World, ctx: &mut Rltk) {
+    let player_ent = ecs.fetch::<Entity>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+
+    let status = match statuses.get(*player_ent) {
+        Some(status) => status,
+        None => return,
+    };
+    for (x, effect) in (22..27).zip(status.effects.iter()) {
+        let (glyph, fg) = match effect.kind {
+            StatusKind::Confused => ('?', RGB::named(rltk::MAGENTA)),
+            StatusKind::Poisoned { .. } => ('*', RGB::named(rltk::GREEN)),
+            StatusKind::Regenerating { .. } => ('+', RGB::named(rltk::RED)),
+            StatusKind::Hasted => ('>', RGB::named(rltk::CYAN)),
+        };
+        ctx.set(x, 43, fg, RGB::named(rltk::BLACK), rltk::to_cp437(glyph));
+    }
+}
+
+/// Every monster, seen or not, as the first letter of its AI state.
+/// Tiles where monsters last saw the player get a '?'.
+fn draw_ai_overlay(ecs: &World, ctx: &mut Rltk) {
+    let positions = ecs.read_storage::<Position>();
+    let ais = ecs.read_storage::<MonsterAI>();
+    let camera = Camera::new(ecs);
+
+    for ai in ais.join() {
+        if let Some((screen_x, screen_y)) = ai.last_seen.and_then(|seen| camera.world_to_screen(seen.x, seen.y)) {
+            ctx.set(screen_x, screen_y, RGB::named(rltk::BLACK), RGB::named(rltk::CYAN), rltk::to_cp437('?'));
+        }
+    }
+    for (pos, ai) in (&positions, &ais).join() {
+        let (glyph, bg) = match ai.state {
+            AiState::Idle => ('I', RGB::named(rltk::GREY)),
+            AiState::Hunting => ('H', RGB::named(rltk::RED)),
+            AiState::Searching => ('S', RGB::named(rltk::YELLOW)),
+            AiState::Fleeing => ('F', RGB::named(rltk::GREEN)),
+        };
+        if let Some((screen_x, screen_y)) = camera.world_to_screen(pos.x, pos.y) {
+            ctx.set(screen_x, screen_y, RGB::named(rltk::BLACK), bg, rltk::to_cp437(glyph));
+        }
     }
 }
 
 fn draw_game_log(ecs: &World, ctx: &mut Rltk) {
     let log = ecs.fetch::<GameLog>();
 
-    let mut y = 44;
-    for s in log.entries.iter().rev() {
-        if y < 49 {
-            ctx.print(2, y, s);
-        }
-        y += 1;
+    for (y, s) in (44..49).zip(log.entries.iter().rev()) {
+        ctx.print(2, y, s);
+    }
+}
+
+/// Shown so that testers can put it into their bug reports
+fn draw_seed(ecs: &World, ctx: &mut Rltk) {
+    let seed = ecs.fetch::<RunSeed>();
+    ctx.print_color(2, 49, RGB::named(rltk::GREY), RGB::named(rltk::BLACK), format!("Seed: {}", seed.0));
+}
+
+/// Highlights the hovered tile, as long as the mouse is over the level
+fn draw_mouse(ecs: &World, ctx: &mut Rltk) {
+    let mouse_pos = ctx.mouse_pos();
+    if mouse_world_pos(ecs, ctx).is_some() {
+        ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::MAGENTA));
     }
 }
 
-fn draw_mouse(_ecs: &World, ctx: &mut Rltk) {
+/// World position of the tile under the mouse. Rets None over the UI panel or off the level.
+pub fn mouse_world_pos(ecs: &World, ctx: &Rltk) -> Option<(i32, i32)> {
+    let level = ecs.fetch::<Level>();
     let mouse_pos = ctx.mouse_pos();
-    ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::MAGENTA));
+    let (x, y) = Camera::new(ecs).screen_to_world(mouse_pos.0, mouse_pos.1)?;
+    if x < level.width && y < level.height {
+        Some((x, y))
+    } else {
+        None
+    }
 }
 
 fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
@@ -48,14 +192,15 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
 
     let mouse_pos = ctx.mouse_pos();
     // Don't draw anything when mouse is out of bounds
-    if mouse_pos.0 > level.width || mouse_pos.1 >= level.height {
-        return;
-    }
+    let (mouse_x, mouse_y) = match mouse_world_pos(ecs, ctx) {
+        Some(world_pos) => world_pos,
+        None => return,
+    };
 
     let mut tooltip: Vec<String> = Vec::new();
     for (name, pos) in (&names, &positions).join() {
         let idx = level.xy_idx(pos.x, pos.y);
-        if pos.x == mouse_pos.0 && pos.y == mouse_pos.1 && level.is_tile_visible(idx) {
+        if pos.x == mouse_x && pos.y == mouse_y && level.is_tile_visible(idx) {
             tooltip.push(name.name.to_string());
         }
     }
@@ -76,32 +221,339 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     if mouse_pos.0 > 40 {
         let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
         let left_x = mouse_pos.0 - width;
-        let mut y = mouse_pos.1;
-        for s in tooltip.iter() {
+        for (y, s) in (mouse_pos.1..).zip(tooltip.iter()) {
             ctx.print_color(left_x, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), s);
             let padding = (width - s.len() as i32) - 1;
             for i in 0..padding {
-                ctx.print_color(arrow_pos.x - i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &" ".to_string());
+                ctx.print_color(arrow_pos.x - i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), " ");
             }
-
-            y += 1;
         }
-        ctx.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &"->".to_string());
+        ctx.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), "->");
 
     } else {
         let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
         let left_x = mouse_pos.0 + 3;
-        let mut y = mouse_pos.1;
-        for s in tooltip.iter() {
+        for (y, s) in (mouse_pos.1..).zip(tooltip.iter()) {
             ctx.print_color(left_x + 1, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), s);
             let padding = (width - s.len() as i32) - 1;
             for i in 0..padding {
-                ctx.print_color(arrow_pos.x + 1 + i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &" ".to_string());
+                ctx.print_color(arrow_pos.x + 1 + i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), " ");
+            }
+        }
+        ctx.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), "<-");
+
+    }
+}
+
+/// The player's carried items as listed by the inventory menu, at most MAX_MENU_ENTRIES of them.
+/// Items with the same name are stacked into a single entry.
+pub fn inventory_stacks(ecs: &World) -> Vec<(String, Vec<Entity>)> {
+    let player_ent = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let names = ecs.read_storage::<GameplayName>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let equipped = ecs.read_storage::<Equipped>();
+
+    // Group carried items by name, keeping the order in which they were found. Worn ones stand apart.
+    let mut stacks: Vec<(String, Vec<Entity>)> = Vec::new();
+    for (ent, carried, name) in (&entities, &backpack, &names).join() {
+        if carried.owner != *player_ent {
+            continue;
+        }
+        let label = if equipped.contains(ent) { format!("{} (worn)", name.name) } else { name.name.clone() };
+        match stacks.iter_mut().find(|(stack_label, _)| *stack_label == label) {
+            Some((_, stack)) => stack.push(ent),
+            None => stacks.push((label, vec![ent])),
+        }
+    }
+    stacks.truncate(MAX_MENU_ENTRIES);
+    stacks
+}
+
+/// Draws a letter-indexed list of the player's carried items and handles the selection keys.
+/// Stacked items get a count.
+pub fn show_inventory(ecs: &World, ctx: &mut Rltk, title: &str) -> (ItemMenuResult, Option<Entity>) {
+    let stacks = inventory_stacks(ecs);
+    let count = stacks.len() as i32;
+    let y = 25 - (count / 2);
+    ctx.draw_box(15, y - 2, 31, count + 3, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, y - 2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), title);
+    ctx.print_color(18, y + count + 1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
+
+    for (i, (name, stack)) in stacks.iter().enumerate() {
+        let y = y + i as i32;
+        let letter = b'a' + i as u8;
+        ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), letter as rltk::FontCharType);
+        ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+
+        if stack.len() > 1 {
+            ctx.print(21, y, format!("{} (x{})", name, stack.len()));
+        } else {
+            ctx.print(21, y, name);
+        }
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(VirtualKeyCode::Escape) => (ItemMenuResult::Cancel, None),
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection > -1 && selection < count {
+                (ItemMenuResult::Selected, Some(stacks[selection as usize].1[0]))
+            } else {
+                (ItemMenuResult::NoResponse, None)
+            }
+        }
+    }
+}
+
+/// Draws a letter-indexed list of the companions the player can give orders to, along with their orders
+pub fn show_companions(ecs: &World, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let entities = ecs.entities();
+    let names = ecs.read_storage::<GameplayName>();
+    let companions = ecs.read_storage::<Companion>();
+
+    // Only those in sight or already recruited, the menu mustn't give away who waits where
+    let orderable = orderable_companions(ecs);
+    let allies: Vec<(Entity, String)> = (&entities, &companions, &names)
+        .join()
+        .filter(|(ent, _, _)| orderable.contains(ent))
+        .map(|(ent, companion, name)| (ent, format!("{} ({})", name.name, companion.order.describe())))
+        .take(MAX_MENU_ENTRIES)
+        .collect();
+
+    let count = allies.len() as i32;
+    let y = 25 - (count / 2);
+    ctx.draw_box(15, y - 2, 31, count + 3, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, y - 2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Give orders to whom?");
+    ctx.print_color(18, y + count + 1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
+
+    for (i, (_, label)) in allies.iter().enumerate() {
+        let y = y + i as i32;
+        let letter = b'a' + i as u8;
+        ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), letter as rltk::FontCharType);
+        ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+        ctx.print(21, y, label);
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(VirtualKeyCode::Escape) => (ItemMenuResult::Cancel, None),
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection > -1 && selection < count {
+                (ItemMenuResult::Selected, Some(allies[selection as usize].0))
+            } else {
+                (ItemMenuResult::NoResponse, None)
+            }
+        }
+    }
+}
+
+/// Orders the companion can be given, drawn on top of the world. Rets None once closed with Escape.
+pub fn order_menu(ecs: &World, ctx: &mut Rltk, companion: Entity, selected: CompanionOrder) -> Option<MenuResult<CompanionOrder>> {
+    let names = ecs.read_storage::<GameplayName>();
+    let name = names.get(companion).map_or("companion", |name| name.name.as_str());
+
+    ctx.draw_box(25, 18, 29, 8, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color_centered(18, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), format!("Orders for the {}", name));
+
+    let options = [
+        (CompanionOrder::Follow, "Follow me"),
+        (CompanionOrder::Stay, "Stay here"),
+        (CompanionOrder::Attack, "Attack..."),
+    ];
+    let result = draw_menu(ctx, 21, &options, selected);
+
+    if ctx.key == Some(VirtualKeyCode::Escape) {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+/// Rets true if the player sees `target` and it is no farther than `range`
+pub fn is_valid_target(ecs: &World, range: i32, target: Point) -> bool {
+    let player_ent = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+
+    let in_sight = viewsheds.get(*player_ent).is_some_and(|vs| vs.visible_tiles.contains(&target));
+    in_sight && rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, target) <= range as f32
+}
+
+/// Tiles of the creatures the player could target within `range`, nearest first
+pub fn targets_in_range(ecs: &World, range: i32) -> Vec<Point> {
+    let player_ent = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let entities = ecs.entities();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let positions = ecs.read_storage::<Position>();
+
+    let mut targets: Vec<Point> = (&entities, &combat_stats, &positions).join()
+        .filter(|(ent, _, _)| *ent != *player_ent)
+        .map(|(_, _, pos)| Point::new(pos.x, pos.y))
+        .filter(|&target| is_valid_target(ecs, range, target))
+        .collect();
+    targets.sort_by(|a, b| {
+        let dist_a = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *a);
+        let dist_b = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *b);
+        dist_a.total_cmp(&dist_b)
+    });
+    targets
+}
+
+/// Highlights the tiles in range and the area of effect around the cursor, which is the only tile that can be fired at.
+/// Tab cycles visible creatures, arrows nudge the cursor, Enter confirms. A click moves the cursor, a click on the cursor confirms.
+pub fn show_targeting(ecs: &World, ctx: &mut Rltk, range: i32, radius: i32, cursor: Point) -> TargetingResult {
+    let player_ent = ecs.fetch::<Entity>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let level = ecs.fetch::<Level>();
+    let camera = Camera::new(ecs);
+
+    ctx.print_color(2, 0, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Select target: Tab/arrows and Enter, or click. ESCAPE to cancel");
+
+    // Everything that can be targeted
+    if let Some(vs) = viewsheds.get(*player_ent) {
+        for tile in vs.visible_tiles.iter() {
+            if !is_valid_target(ecs, range, *tile) {
+                continue;
+            }
+            if let Some((screen_x, screen_y)) = camera.world_to_screen(tile.x, tile.y) {
+                ctx.set_bg(screen_x, screen_y, RGB::named(rltk::BLUE));
+            }
+        }
+    }
+
+    let is_cursor_valid = is_valid_target(ecs, range, cursor);
+    if is_cursor_valid {
+        for tile in level.area_of_effect(cursor, radius).iter() {
+            if let Some((screen_x, screen_y)) = camera.world_to_screen(tile.x, tile.y) {
+                ctx.set_bg(screen_x, screen_y, RGB::named(rltk::ORANGE));
+            }
+        }
+    }
+    if let Some((screen_x, screen_y)) = camera.world_to_screen(cursor.x, cursor.y) {
+        let bg = if is_cursor_valid { RGB::named(rltk::CYAN) } else { RGB::named(rltk::RED) };
+        ctx.set_bg(screen_x, screen_y, bg);
+    }
+
+    let clicked = if ctx.left_click { mouse_world_pos(ecs, ctx).map(|(x, y)| Point::new(x, y)) } else { None };
+    targeting_input(ecs, range, cursor, ctx.key, clicked)
+}
+
+/// Where the targeting cursor goes for the pressed key or clicked tile. Only ever fires at the cursor the player saw,
+/// so a click elsewhere just moves the cursor there.
+pub fn targeting_input(ecs: &World, range: i32, cursor: Point, key: Option<VirtualKeyCode>, clicked: Option<Point>) -> TargetingResult {
+    if let Some(tile) = clicked {
+        return if tile == cursor && is_valid_target(ecs, range, tile) {
+            TargetingResult::Selected { target: cursor }
+        } else if is_valid_target(ecs, range, tile) {
+            TargetingResult::NoResponse { cursor: tile }
+        } else {
+            TargetingResult::NoResponse { cursor }
+        };
+    }
+
+    let level = ecs.fetch::<Level>();
+    let nudge = |dx: i32, dy: i32| Point::new((cursor.x + dx).clamp(0, level.width - 1), (cursor.y + dy).clamp(0, level.height - 1));
+    match key {
+        None => TargetingResult::NoResponse { cursor },
+        Some(VirtualKeyCode::Escape) => TargetingResult::Cancel,
+        Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::F) => {
+            if is_valid_target(ecs, range, cursor) {
+                TargetingResult::Selected { target: cursor }
+            } else {
+                TargetingResult::NoResponse { cursor }
             }
+        }
+        Some(VirtualKeyCode::Tab) => {
+            let targets = targets_in_range(ecs, range);
+            let next_idx = targets.iter().position(|&target| target == cursor).map_or(0, |idx| idx + 1);
+            match targets.get(next_idx % targets.len().max(1)) {
+                Some(&target) => TargetingResult::NoResponse { cursor: target },
+                None => TargetingResult::NoResponse { cursor },
+            }
+        }
+        Some(VirtualKeyCode::Left) => TargetingResult::NoResponse { cursor: nudge(-1, 0) },
+        Some(VirtualKeyCode::Right) => TargetingResult::NoResponse { cursor: nudge(1, 0) },
+        Some(VirtualKeyCode::Up) => TargetingResult::NoResponse { cursor: nudge(0, -1) },
+        Some(VirtualKeyCode::Down) => TargetingResult::NoResponse { cursor: nudge(0, 1) },
+        Some(_) => TargetingResult::NoResponse { cursor },
+    }
+}
 
-            y += 1;
+/// Draws centered options starting at `y`, highlighting `selected`, and moves the cursor with Up/Down.
+/// If `selected` isn't among the options, the first one is used instead.
+fn draw_menu<T: PartialEq + Copy>(ctx: &mut Rltk, y: i32, options: &[(T, &str)], selected: T) -> MenuResult<T> {
+    let selected_idx = options.iter().position(|(option, _)| *option == selected).unwrap_or(0);
+
+    for (i, (_, label)) in options.iter().enumerate() {
+        let fg = if i == selected_idx { RGB::named(rltk::MAGENTA) } else { RGB::named(rltk::WHITE) };
+        ctx.print_color_centered(y + i as i32, fg, RGB::named(rltk::BLACK), label);
+    }
+
+    let selected = options[selected_idx].0;
+    match ctx.key {
+        Some(VirtualKeyCode::Up) | Some(VirtualKeyCode::W) => {
+            let prev_idx = (selected_idx + options.len() - 1) % options.len();
+            MenuResult::NoSelection { selected: options[prev_idx].0 }
+        }
+        Some(VirtualKeyCode::Down) | Some(VirtualKeyCode::S) => {
+            let next_idx = (selected_idx + 1) % options.len();
+            MenuResult::NoSelection { selected: options[next_idx].0 }
         }
-        ctx.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &"<-".to_string());
+        Some(VirtualKeyCode::Return) => MenuResult::Selected { selected },
+        _ => MenuResult::NoSelection { selected },
+    }
+}
+
+pub fn main_menu(ecs: &World, ctx: &mut Rltk, selected: MainMenuSelection) -> MenuResult<MainMenuSelection> {
+    ctx.print_color_centered(15, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Rust Roguelike");
+    ctx.print_color_centered(17, RGB::named(rltk::GREY), RGB::named(rltk::BLACK), "Use Up/Down and Enter");
+
+    // Latest news, e.g. why the save couldn't be continued
+    if let Some(entry) = ecs.try_fetch::<GameLog>().and_then(|log| log.entries.last().cloned()) {
+        ctx.print_color_centered(40, RGB::named(rltk::ORANGE), RGB::named(rltk::BLACK), &entry);
+    }
 
+    let mut options = vec![(MainMenuSelection::NewGame, "Begin New Game")];
+    // Only offer to continue when there's something to continue
+    if save_load::does_save_exist() {
+        options.push((MainMenuSelection::Continue, "Continue"));
     }
-}
\ No newline at end of file
+    options.push((MainMenuSelection::Quit, "Quit"));
+
+    draw_menu(ctx, 24, &options, selected)
+}
+
+/// Pause overlay, drawn on top of the world. Escape resumes the game right away.
+pub fn pause_menu(ctx: &mut Rltk, selected: PauseMenuSelection) -> MenuResult<PauseMenuSelection> {
+    ctx.draw_box(25, 18, 29, 8, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color_centered(18, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Paused");
+
+    let options = [
+        (PauseMenuSelection::Resume, "Resume"),
+        (PauseMenuSelection::SaveAndExit, "Save and Exit to Menu"),
+    ];
+    let result = draw_menu(ctx, 21, &options, selected);
+
+    if ctx.key == Some(VirtualKeyCode::Escape) {
+        MenuResult::Selected { selected: PauseMenuSelection::Resume }
+    } else {
+        result
+    }
+}
+
+pub fn game_over(ctx: &mut Rltk) -> GameOverResult {
+    ctx.print_color_centered(15, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Your journey has ended!");
+    ctx.print_color_centered(17, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "You are dead. Not a big surprise!");
+    ctx.print_color_centered(20, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), "Press any key to return to the menu.");
+
+    match ctx.key {
+        None => GameOverResult::NoSelection,
+        Some(_) => GameOverResult::QuitToMenu,
+    }
+}
@@ -0,0 +1,90 @@
+use specs::prelude::*;
+
+use crate::game_state::{destroy_dead_entities, GameStatus, PlayerCommand, State};
+use crate::map_builders::MapOptions;
+use crate::raws::Raws;
+use crate::seed::RunSeed;
+
+/// Runs the game without a window: feeds player commands into the turn machine
+/// and lets the caller inspect the resulting World
+pub struct Simulation {
+    pub state: State,
+}
+
+impl Simulation {
+    /// Starts a new run from the given seed and plays it up to the first player input.
+    /// The map options are taken as given, whatever the CLI args or env vars say.
+    pub fn new(raws: Raws, seed: RunSeed, map_options: MapOptions) -> Self {
+        let mut state = State::new(raws);
+        state.ecs.insert(map_options);
+        state.new_game_with_seed(seed);
+
+        let mut sim = Simulation { state };
+        sim.run_until_input(GameStatus::PreTurn);
+        sim
+    }
+
+    pub fn ecs(&self) -> &World {
+        &self.state.ecs
+    }
+
+    pub fn ecs_mut(&mut self) -> &mut World {
+        &mut self.state.ecs
+    }
+
+    pub fn status(&self) -> GameStatus {
+        *self.state.ecs.fetch::<GameStatus>()
+    }
+
+    /// Plays one player command along with the monsters' response.
+    /// Does nothing once the run is over.
+    pub fn step(&mut self, command: PlayerCommand) -> GameStatus {
+        if self.status() != GameStatus::AwaitingInput {
+            return self.status();
+        }
+        let status = self.state.apply_command(command);
+        self.run_until_input(status)
+    }
+
+    /// Plays the same command a number of times, stopping early if the run ends
+    pub fn step_n(&mut self, command: PlayerCommand, turns: usize) -> GameStatus {
+        for _ in 0..turns {
+            if self.step(command) != GameStatus::AwaitingInput {
+                break;
+            }
+        }
+        self.status()
+    }
+
+    /// Plays scripted commands in order, stopping early if the run ends
+    pub fn run_script(&mut self, commands: &[PlayerCommand]) -> GameStatus {
+        for command in commands.iter() {
+            if self.step(*command) != GameStatus::AwaitingInput {
+                break;
+            }
+        }
+        self.status()
+    }
+
+    /// Same bookkeeping State::tick does every frame, just without waiting for the next one
+    fn run_until_input(&mut self, mut status: GameStatus) -> GameStatus {
+        loop {
+            *self.state.ecs.write_resource::<GameStatus>() = status;
+            destroy_dead_entities(&mut self.state.ecs);
+            status = self.status();
+
+            let needs_no_input = matches!(
+                status,
+                GameStatus::PreTurn
+                    | GameStatus::PlayerTurn
+                    | GameStatus::MonsterTurn
+                    | GameStatus::Ticking
+                    | GameStatus::NextLevel
+            );
+            if !needs_no_input {
+                return status;
+            }
+            status = self.state.advance_turn(status);
+        }
+    }
+}
@@ -1,34 +1,57 @@
-use crate::util::rect::Rect;
-use rltk::{to_cp437, Algorithm2D, BaseMap, Point, RandomNumberGenerator, Rltk, RGB};
+use crate::camera::{Camera, VIEWPORT_HEIGHT, VIEWPORT_WIDTH};
+use crate::util::{bitset::BitSet, rect::Rect};
+use rltk::{
+    field_of_view, line2d, to_cp437, Algorithm2D, BaseMap, FontCharType, LineAlg, Point, Rltk, RGB,
+};
+use serde::{Deserialize, Serialize};
 use specs::{Entity, World};
-use std::cmp::{max, min};
-use std::collections::HashSet;
 
-// Constants
-pub const MAP_WIDTH_PIX: usize = 80;
-pub const MAP_HEIGHT_PIX: usize = 43;
-pub const MAP_PIXELCOUNT: usize = MAP_WIDTH_PIX * MAP_HEIGHT_PIX;
+pub mod distance_map;
+pub use distance_map::*;
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
+    DownStairs,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Level {
     pub tiles: Vec<TileType>,
+    /// Only filled in by room-based builders
     pub rooms: Vec<Rect>,
     pub width: i32,
     pub height: i32,
-    /// Has the same len as the total level indices
-    /// Each tile is either false (not revealed) or true
-    /// These tiles were seen by player at some point
-    revealed_tile_indices: HashSet<usize>,
+    /// How deep into the dungeon this level is, starting at 1
+    pub depth: i32,
+    /// One bit per tile, set if the player has seen it at some point
+    revealed_tile_indices: BitSet,
     /// These tile indices are CURRENTLY VISIBLE by the player
-    fov_tile_indices: HashSet<usize>,
+    fov_tile_indices: BitSet,
+    /// Walls never move, so they are indexed once and copied over the blocked tiles every turn.
+    /// Rebuilt by index_walls, so it is not saved
+    #[serde(skip)]
+    wall_tile_indices: BitSet,
     /// Keeping track of tiles blocked by some entity (preventing movement)
-    blocked_tile_indices: HashSet<usize>,
+    /// Rebuilt by MapIndexingSystem, so it is not saved
+    #[serde(skip)]
+    blocked_tile_indices: BitSet,
+    /// Rebuilt by MapIndexingSystem, so it is not saved (has to be resized after loading)
+    #[serde(skip)]
     pub tile_content: Vec<Vec<Entity>>,
+    /// Rebuilt by DistanceMapSystem whenever their goals move, so they are not saved
+    #[serde(skip)]
+    pub to_player: DistanceMap,
+    #[serde(skip)]
+    pub from_player: DistanceMap,
+    #[serde(skip)]
+    pub to_items: DistanceMap,
+    /// Goals the distance maps were last built for, None until they are built
+    #[serde(skip)]
+    player_map_goal: Option<usize>,
+    #[serde(skip)]
+    item_map_goals: Option<Vec<usize>>,
 }
 
 //--------------START RLTK Trait implementations
@@ -40,210 +63,219 @@ impl Algorithm2D for Level {
 }
 impl BaseMap for Level {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx as usize] == TileType::Wall
+        self.tiles[idx] == TileType::Wall
     }
 
     fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        self.exits_where(idx, |x, y| self.is_valid_exit(x, y))
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        let w = self.width as usize;
+        let p1 = Point::new(idx1 % w, idx1 / w);
+        let p2 = Point::new(idx2 % w, idx2 / w);
+        rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
+    }
+}
+
+//--------------END RLTK Trait implementations
+
+impl Level {
+    /// Neighbours of `idx` for which `is_open` holds, with the cost of stepping there
+    fn exits_where(
+        &self,
+        idx: usize,
+        is_open: impl Fn(i32, i32) -> bool,
+    ) -> rltk::SmallVec<[(usize, f32); 10]> {
         let mut exits = rltk::SmallVec::new();
         let x = idx as i32 % self.width;
         let y = idx as i32 / self.width;
         let w = self.width as usize;
 
         // Cardinal directions
-        if self.is_valid_exit(x - 1, y) {
+        if is_open(x - 1, y) {
             exits.push((idx - 1, 1.0));
         }
-        if self.is_valid_exit(x + 1, y) {
+        if is_open(x + 1, y) {
             exits.push((idx + 1, 1.0));
         }
-        if self.is_valid_exit(x, y - 1) {
+        if is_open(x, y - 1) {
             exits.push((idx - w, 1.0));
         }
-        if self.is_valid_exit(x, y + 1) {
+        if is_open(x, y + 1) {
             exits.push((idx + w, 1.0));
         }
 
         // Diagonals
-        if self.is_valid_exit(x - 1, y - 1) {
+        if is_open(x - 1, y - 1) {
             exits.push(((idx - w) - 1, 1.45));
         }
-        if self.is_valid_exit(x + 1, y - 1) {
+        if is_open(x + 1, y - 1) {
             exits.push(((idx - w) + 1, 1.45));
         }
-        if self.is_valid_exit(x - 1, y + 1) {
+        if is_open(x - 1, y + 1) {
             exits.push(((idx + w) - 1, 1.45));
         }
-        if self.is_valid_exit(x + 1, y + 1) {
+        if is_open(x + 1, y + 1) {
             exits.push(((idx + w) + 1, 1.45));
         }
 
         exits
     }
-
-    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
-        let w = self.width as usize;
-        let p1 = Point::new(idx1 % w, idx1 / w);
-        let p2 = Point::new(idx2 % w, idx2 / w);
-        rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
+    /// Like get_available_exits, but only walls are in the way, not entities
+    pub fn walkable_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        self.exits_where(idx, |x, y| {
+            self.is_inside_border(x, y) && !self.is_opaque(self.xy_idx(x, y))
+        })
     }
-}
-
-//--------------END RLTK Trait implementations
-
-impl Level {
     pub fn clear_tiles_content(&mut self) {
         for content in self.tile_content.iter_mut() {
             content.clear();
         }
     }
-    pub fn block_walls_only(&mut self) {
-        self.blocked_tile_indices.clear();
+    /// Re-reads the walls from the tiles and blocks only them.
+    /// Has to be called whenever tiles change, e.g. by builders and after loading.
+    pub fn index_walls(&mut self) {
+        self.wall_tile_indices = BitSet::new(self.tiles.len());
         for (i, tile) in self.tiles.iter().enumerate() {
             if *tile == TileType::Wall {
-                self.blocked_tile_indices.insert(i);
+                self.wall_tile_indices.insert(i);
             }
         }
+        self.block_walls_only();
+    }
+    /// Unblocks everything but the walls indexed by index_walls
+    pub fn block_walls_only(&mut self) {
+        self.blocked_tile_indices.copy_from(&self.wall_tile_indices);
     }
     pub fn block_tile(&mut self, idx: usize) {
         self.blocked_tile_indices.insert(idx);
     }
     pub fn is_tile_blocked(&self, idx: usize) -> bool {
-        self.blocked_tile_indices.contains(&idx)
+        self.blocked_tile_indices.contains(idx)
     }
     pub fn is_valid_exit(&self, x: i32, y: i32) -> bool {
-        if x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1 {
+        if !self.is_inside_border(x, y) {
             false
         } else {
             let idx = self.xy_idx(x, y);
-            !self.is_tile_blocked(idx as usize)
+            !self.is_tile_blocked(idx)
         }
     }
+    fn is_inside_border(&self, x: i32, y: i32) -> bool {
+        !(x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1)
+    }
     pub fn clear_fov_tiles(&mut self) {
         self.fov_tile_indices.clear();
     }
     pub fn is_tile_revealed(&self, idx: usize) -> bool {
-        self.revealed_tile_indices.contains(&idx)
+        self.revealed_tile_indices.contains(idx)
     }
     pub fn is_tile_visible(&self, idx: usize) -> bool {
-        self.fov_tile_indices.contains(&idx)
+        self.fov_tile_indices.contains(idx)
     }
     pub fn reveal_tile(&mut self, idx: usize) {
         self.revealed_tile_indices.insert(idx);
         // Also add to fov (visible) tiles when we reveal them
         self.fov_tile_indices.insert(idx);
     }
-    pub fn xy_idx(&self, x: i32, y: i32) -> usize {
-        x as usize + (y as usize * self.width as usize)
+    /// Tiles a projectile flies over on its way from `from` to `to`, the start excluded.
+    /// Cut short before the first wall or the level edge. Entities aren't checked here.
+    pub fn line_of_fire(&self, from: Point, to: Point) -> Vec<Point> {
+        line2d(LineAlg::Bresenham, from, to)
+            .into_iter()
+            .skip(1)
+            .take_while(|p| {
+                let is_inside = p.x >= 0 && p.x < self.width && p.y >= 0 && p.y < self.height;
+                is_inside && !self.is_opaque(self.xy_idx(p.x, p.y))
+            })
+            .collect()
     }
-    fn apply_room(&mut self, room: &Rect) {
-        for y in room.y1 + 1..=room.y2 {
-            for x in room.x1 + 1..=room.x2 {
-                let tile_idx = self.xy_idx(x, y);
-                // Just a small self-testing piece of code
-                assert!(tile_idx < self.tiles.len());
-                self.tiles[tile_idx] = TileType::Floor;
-            }
+    /// True if a shot from `from` would reach `to` without hitting a wall or a blocked tile on the way
+    pub fn has_clear_shot(&self, from: Point, to: Point) -> bool {
+        let line = self.line_of_fire(from, to);
+        if line.last() != Some(&to) {
+            return false;
         }
+        line[..line.len() - 1]
+            .iter()
+            .all(|p| !self.is_tile_blocked(self.xy_idx(p.x, p.y)))
     }
-
-    fn apply_horiz_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
-        for x in min(x1, x2)..=max(x1, x2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < MAP_PIXELCOUNT {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
+    /// Tiles caught in a blast of `radius` centered at `center`. Walls shield whatever is behind them.
+    pub fn area_of_effect(&self, center: Point, radius: i32) -> Vec<Point> {
+        if radius <= 0 {
+            return vec![center];
         }
+        field_of_view(center, radius, self)
     }
-
-    fn apply_vert_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
-        for y in min(y1, y2)..=max(y1, y2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < MAP_PIXELCOUNT {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
-        }
+    pub fn xy_idx(&self, x: i32, y: i32) -> usize {
+        x as usize + (y as usize * self.width as usize)
+    }
+    pub fn idx_xy(&self, idx: usize) -> (i32, i32) {
+        (idx as i32 % self.width, idx as i32 / self.width)
     }
-    pub fn new() -> Self {
-        let mut level = Level {
-            tiles: vec![TileType::Wall; MAP_PIXELCOUNT],
+    /// Creates a width x height level of solid wall, to be carved out by a `MapBuilder`
+    pub fn new(width: i32, height: i32, depth: i32) -> Self {
+        let tile_count = (width * height) as usize;
+        Level {
+            tiles: vec![TileType::Wall; tile_count],
             rooms: Vec::new(),
-            width: MAP_WIDTH_PIX as i32,
-            height: MAP_HEIGHT_PIX as i32,
-            revealed_tile_indices: HashSet::new(),
-            fov_tile_indices: HashSet::new(),
-            blocked_tile_indices: HashSet::new(),
-            tile_content: vec![Vec::new(); MAP_PIXELCOUNT],
-        };
-        const NUM_MAX_ROOMS: u8 = 30;
-        const MIN_ROOM_SIZE: u8 = 6;
-        const MAX_ROOM_SIZE: u8 = 10;
-
-        let mut rng = RandomNumberGenerator::new();
-
-        for _ in 0..NUM_MAX_ROOMS {
-            let w = rng.range(MIN_ROOM_SIZE, MAX_ROOM_SIZE) as i32;
-            let h = rng.range(MIN_ROOM_SIZE, MAX_ROOM_SIZE) as i32;
-            let x = rng.roll_dice(1, MAP_WIDTH_PIX as i32 - w - 1) - 1;
-            let y = rng.roll_dice(1, MAP_HEIGHT_PIX as i32 - h - 1) - 1;
-            let new_room = Rect::new(x, y, w, h);
-
-            let no_intersections = level
-                .rooms
-                .iter()
-                .find(|&room| new_room.intersects(room))
-                .is_none();
-
-            // TODO later: we could sometimes allow intersections
-            // to create more interesting rooms.
-            if !no_intersections {
-                continue;
-            }
-
-            level.apply_room(&new_room);
-
-            // Corridorize
-            if !level.rooms.is_empty() {
-                add_corridors(&new_room, &mut rng, &mut level);
-            }
-
-            level.rooms.push(new_room);
+            width,
+            height,
+            depth,
+            revealed_tile_indices: BitSet::new(tile_count),
+            fov_tile_indices: BitSet::new(tile_count),
+            wall_tile_indices: BitSet::new(tile_count),
+            blocked_tile_indices: BitSet::new(tile_count),
+            tile_content: vec![Vec::new(); tile_count],
+            to_player: DistanceMap::default(),
+            from_player: DistanceMap::default(),
+            to_items: DistanceMap::default(),
+            player_map_goal: None,
+            item_map_goals: None,
         }
-
-        level
-    }
-}
-
-fn add_corridors(new_room: &Rect, rng: &mut RandomNumberGenerator, level: &mut Level) {
-    let (new_x, new_y) = new_room.get_center();
-    let (prev_x, prev_y) = level.rooms[level.rooms.len() - 1].get_center();
-    if rng.range(0, 2) == 1 {
-        level.apply_horiz_tunnel(prev_x, new_x, prev_y);
-        level.apply_vert_tunnel(prev_y, new_y, new_x);
-    } else {
-        level.apply_vert_tunnel(prev_y, new_y, prev_x);
-        level.apply_horiz_tunnel(prev_x, new_x, new_y);
     }
 }
 
+/// Draws the part of the level that is seen through the camera
 pub fn draw_tiles(ecs: &World, ctx: &mut Rltk) {
     let level = ecs.fetch::<Level>();
+    let camera = Camera::new(ecs);
 
-    let (mut x, mut y) = (0, 0);
-    for (idx, tile) in level.tiles.iter().enumerate() {
-        // Render a type depending on its type
-        if level.is_tile_revealed(idx) {
-            draw_tile(level.is_tile_visible(idx), tile, ctx, x, y);
-        }
+    for_each_drawn_tile(&level, &camera, |x, y, glyph, fg| {
+        ctx.set(x, y, fg, RGB::from_f32(0., 0., 0.), glyph);
+    });
+}
 
-        x += 1;
-        if x > 79 {
-            x = 0;
-            y += 1;
+/// Calls `draw` with the screen position, glyph and color of every revealed tile
+/// seen through the camera. Split from draw_tiles so it can be measured without a window.
+pub fn for_each_drawn_tile(
+    level: &Level,
+    camera: &Camera,
+    mut draw: impl FnMut(i32, i32, FontCharType, RGB),
+) {
+    for y in 0..VIEWPORT_HEIGHT {
+        for x in 0..VIEWPORT_WIDTH {
+            let (world_x, world_y) = match camera.screen_to_world(x, y) {
+                Some(world_pos) => world_pos,
+                None => continue,
+            };
+            // Level may be smaller than the viewport
+            if world_x >= level.width || world_y >= level.height {
+                continue;
+            }
+
+            // Render a type depending on its type
+            let idx = level.xy_idx(world_x, world_y);
+            if level.is_tile_revealed(idx) {
+                let (glyph, fg) = tile_appearance(level.is_tile_visible(idx), &level.tiles[idx]);
+                draw(x, y, glyph, fg);
+            }
         }
     }
 }
 
-fn draw_tile(is_visible: bool, tile: &TileType, ctx: &mut Rltk, x: i32, y: i32) {
+fn tile_appearance(is_visible: bool, tile: &TileType) -> (FontCharType, RGB) {
     let glyph;
     let mut fg;
     match tile {
@@ -255,6 +287,10 @@ fn draw_tile(is_visible: bool, tile: &TileType, ctx: &mut Rltk, x: i32, y: i32)
             glyph = to_cp437('#');
             fg = RGB::from_f32(0.8, 0.8, 0.8);
         }
+        TileType::DownStairs => {
+            glyph = to_cp437('>');
+            fg = RGB::from_f32(0., 1.0, 1.0);
+        }
     }
     if !is_visible {
         const VIS_DARKEN: f32 = 0.4;
@@ -262,5 +298,5 @@ fn draw_tile(is_visible: bool, tile: &TileType, ctx: &mut Rltk, x: i32, y: i32)
         fg.g -= VIS_DARKEN;
         fg.b -= VIS_DARKEN;
     }
-    ctx.set(x, y, fg, RGB::from_f32(0., 0., 0.), glyph);
+    (glyph, fg)
 }
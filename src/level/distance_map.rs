@@ -0,0 +1,138 @@
+use super::Level;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Flee maps scale the distances by this before relaxing them again. Being far from the threat
+/// then outweighs the few steps it takes to get past it, so monsters don't back into dead ends.
+const FLEE_SCALE: f32 = -1.2;
+
+/// Dijkstra map: the walking distance of every tile to the nearest goal.
+/// Only walls are in the way, so monsters standing around don't cut the map apart.
+/// Monsters roll downhill on it to approach the goals, or on its flee map to get away from them.
+#[derive(Clone, Default)]
+pub struct DistanceMap {
+    values: Vec<f32>,
+}
+
+impl DistanceMap {
+    /// Value of the tiles the goals can't be reached from
+    pub const UNREACHABLE: f32 = f32::MAX;
+
+    /// Map towards the given goal tile indices
+    pub fn new(level: &Level, goals: &[usize]) -> Self {
+        let mut map = DistanceMap {
+            values: vec![Self::UNREACHABLE; level.tiles.len()],
+        };
+        for &idx in goals {
+            map.values[idx] = 0.0;
+        }
+        map.relax(level);
+        map
+    }
+
+    /// Map leading away from this map's goals
+    pub fn flee_map(&self, level: &Level) -> Self {
+        let mut map = DistanceMap {
+            values: self
+                .values
+                .iter()
+                .map(|&value| {
+                    if value == Self::UNREACHABLE {
+                        value
+                    } else {
+                        value * FLEE_SCALE
+                    }
+                })
+                .collect(),
+        };
+        map.relax(level);
+        map
+    }
+
+    /// Rets UNREACHABLE for tiles outside of the map, e.g. before it was built
+    pub fn value(&self, idx: usize) -> f32 {
+        self.values.get(idx).copied().unwrap_or(Self::UNREACHABLE)
+    }
+
+    /// Free neighbouring tile with the lowest value, as long as it is lower than the current one.
+    /// Rets None when already at a goal or when every way down is blocked.
+    pub fn downhill_step(&self, level: &Level, from: usize) -> Option<usize> {
+        let current = self.value(from);
+        rltk::BaseMap::get_available_exits(level, from)
+            .into_iter()
+            .map(|(idx, _)| (idx, self.value(idx)))
+            .filter(|(_, value)| *value < current)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(idx, _)| idx)
+    }
+
+    /// Dijkstra from every tile that has a value, lowering its neighbours to that value plus the step cost
+    fn relax(&mut self, level: &Level) {
+        let mut open: BinaryHeap<OpenTile> = self
+            .values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != Self::UNREACHABLE)
+            .map(|(idx, &value)| OpenTile { idx, value })
+            .collect();
+
+        while let Some(OpenTile { idx, value }) = open.pop() {
+            // Lowered again since it was pushed
+            if value > self.values[idx] {
+                continue;
+            }
+            for (next_idx, cost) in level.walkable_exits(idx) {
+                let next_value = value + cost;
+                if next_value < self.values[next_idx] {
+                    self.values[next_idx] = next_value;
+                    open.push(OpenTile {
+                        idx: next_idx,
+                        value: next_value,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Level {
+    /// Rebuilds the maps to and from the player and the map to the items lying around,
+    /// skipping those whose goals haven't moved since they were last built
+    pub fn refresh_distance_maps(&mut self, player_idx: usize, mut item_idx: Vec<usize>) {
+        if self.player_map_goal != Some(player_idx) {
+            self.to_player = DistanceMap::new(self, &[player_idx]);
+            self.from_player = self.to_player.flee_map(self);
+            self.player_map_goal = Some(player_idx);
+        }
+
+        item_idx.sort_unstable();
+        if self.item_map_goals.as_ref() != Some(&item_idx) {
+            self.to_items = DistanceMap::new(self, &item_idx);
+            self.item_map_goals = Some(item_idx);
+        }
+    }
+}
+
+/// Heap entry, ordered so that the lowest value pops first
+#[derive(PartialEq)]
+struct OpenTile {
+    idx: usize,
+    value: f32,
+}
+
+impl Eq for OpenTile {}
+
+impl Ord for OpenTile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .value
+            .total_cmp(&self.value)
+            .then_with(|| self.idx.cmp(&other.idx))
+    }
+}
+
+impl PartialOrd for OpenTile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -1 +1,17 @@
+//! Game logic of the roguelike. The binary only opens the window and runs the main loop,
+//! so the same ECS can be driven headlessly, e.g. from integration tests.
 
+pub mod camera;
+pub mod components;
+pub mod game_log;
+pub mod game_state;
+pub mod gui;
+pub mod headless;
+pub mod level;
+pub mod map_builders;
+pub mod raws;
+pub mod save_load;
+pub mod seed;
+pub mod spawner;
+pub mod systems;
+pub mod util;
@@ -0,0 +1,125 @@
+use rltk::RandomNumberGenerator;
+
+use crate::level::Level;
+use crate::util::arg_or_env;
+
+pub mod bsp_dungeon;
+pub mod cellular_automata;
+pub mod common;
+pub mod drunkard;
+pub mod simple_map;
+
+pub use bsp_dungeon::*;
+pub use cellular_automata::*;
+pub use drunkard::*;
+pub use simple_map::*;
+
+/// Env var that can be used instead of the `--builder` CLI arg
+const BUILDER_ENV_VAR: &str = "ROGUELIKE_BUILDER";
+/// Env var that can be used instead of the `--map-size` CLI arg
+const MAP_SIZE_ENV_VAR: &str = "ROGUELIKE_MAP_SIZE";
+
+/// Size of the classic levels, fitting the screen exactly
+pub const DEFAULT_MAP_WIDTH: i32 = 80;
+pub const DEFAULT_MAP_HEIGHT: i32 = 43;
+/// Caves need more room to get interesting, the camera scrolls over them
+const CAVE_MAP_WIDTH: i32 = 120;
+const CAVE_MAP_HEIGHT: i32 = 64;
+/// Builders carve a wall border and need some room inside of it
+const MIN_MAP_SIZE: i32 = 20;
+
+/// A map generation algorithm. Builds a single level and tells where its content should go.
+pub trait MapBuilder {
+    /// Carves the level out of solid wall, placing the stairs down somewhere reachable
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    fn get_level(&self) -> Level;
+    /// Groups of floor tile indices, each one populated with monsters and items on its own
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>>;
+    fn get_player_start(&self) -> (i32, i32);
+}
+
+/// Builder and level size forced on every depth, instead of the builders' own picks
+#[derive(Clone, Debug, Default)]
+pub struct MapOptions {
+    /// One of `rooms`, `bsp`, `cellular` or `drunkard`
+    pub builder: Option<String>,
+    pub size: Option<(i32, i32)>,
+}
+
+impl MapOptions {
+    /// `--builder <name>` (or ROGUELIKE_BUILDER) and `--map-size <w>x<h>` (or ROGUELIKE_MAP_SIZE)
+    pub fn from_args_or_env() -> Self {
+        MapOptions {
+            builder: arg_or_env("builder", BUILDER_ENV_VAR),
+            size: arg_or_env("map-size", MAP_SIZE_ENV_VAR).and_then(|value| parse_size(&value)),
+        }
+    }
+}
+
+/// Picks the builder for the given depth, unless the options force one.
+/// A forced size overrides the builder's own level size.
+pub fn new_builder(
+    depth: i32,
+    options: &MapOptions,
+    rng: &mut RandomNumberGenerator,
+) -> Box<dyn MapBuilder> {
+    let forced_size = options.size;
+
+    if let Some(name) = &options.builder {
+        match builder_by_name(name, depth, forced_size) {
+            Some(builder) => return builder,
+            None => eprintln!("Ignoring unknown map builder {:?}", name),
+        }
+    }
+
+    // First level is always the classic one, deeper ones get more varied
+    let name = if depth == 1 {
+        "rooms"
+    } else {
+        match rng.roll_dice(1, 4) {
+            1 => "rooms",
+            2 => "bsp",
+            3 => "cellular",
+            _ => "drunkard",
+        }
+    };
+    builder_by_name(name, depth, forced_size).expect("Builder names above should all be known")
+}
+
+fn builder_by_name(
+    name: &str,
+    depth: i32,
+    forced_size: Option<(i32, i32)>,
+) -> Option<Box<dyn MapBuilder>> {
+    let (rooms_w, rooms_h) = forced_size.unwrap_or((DEFAULT_MAP_WIDTH, DEFAULT_MAP_HEIGHT));
+    let (caves_w, caves_h) = forced_size.unwrap_or((CAVE_MAP_WIDTH, CAVE_MAP_HEIGHT));
+    match name.trim() {
+        "rooms" => Some(Box::new(SimpleMapBuilder::new(rooms_w, rooms_h, depth))),
+        "bsp" => Some(Box::new(BspDungeonBuilder::new(rooms_w, rooms_h, depth))),
+        "cellular" => Some(Box::new(CellularAutomataBuilder::new(
+            caves_w, caves_h, depth,
+        ))),
+        "drunkard" => Some(Box::new(DrunkardsWalkBuilder::new(caves_w, caves_h, depth))),
+        _ => None,
+    }
+}
+
+/// Parses `<width>x<height>`, e.g. `200x200`
+fn parse_size(value: &str) -> Option<(i32, i32)> {
+    let parsed = value
+        .trim()
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)));
+    match parsed {
+        Some((width, height)) if width >= MIN_MAP_SIZE && height >= MIN_MAP_SIZE => {
+            Some((width, height))
+        }
+        _ => {
+            eprintln!(
+                "Ignoring map size {:?}, it has to look like 120x64 and be at least {}x{}",
+                value, MIN_MAP_SIZE, MIN_MAP_SIZE
+            );
+            None
+        }
+    }
+}
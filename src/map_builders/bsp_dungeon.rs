@@ -0,0 +1,152 @@
+use rltk::RandomNumberGenerator;
+
+use super::common::{
+    apply_horiz_tunnel, apply_room_to_level, apply_vert_tunnel, ensure_a_room, room_spawn_regions,
+};
+use super::MapBuilder;
+use crate::level::{Level, TileType};
+use crate::util::Rect;
+
+/// How many times we try to place a room
+const NUM_ROOM_ATTEMPTS: u16 = 240;
+
+/// Binary space partitioning: the map is recursively split into rectangles
+/// and rooms are placed inside of them, so they never overlap
+pub struct BspDungeonBuilder {
+    level: Level,
+    rects: Vec<Rect>,
+}
+
+impl BspDungeonBuilder {
+    pub fn new(width: i32, height: i32, depth: i32) -> Self {
+        BspDungeonBuilder {
+            level: Level::new(width, height, depth),
+            rects: Vec::new(),
+        }
+    }
+
+    /// Splits the rect into 4 quarters
+    fn add_subrects(&mut self, rect: &Rect) {
+        let width = i32::abs(rect.x1 - rect.x2);
+        let height = i32::abs(rect.y1 - rect.y2);
+        let half_width = i32::max(width / 2, 1);
+        let half_height = i32::max(height / 2, 1);
+
+        self.rects
+            .push(Rect::new(rect.x1, rect.y1, half_width, half_height));
+        self.rects.push(Rect::new(
+            rect.x1,
+            rect.y1 + half_height,
+            half_width,
+            half_height,
+        ));
+        self.rects.push(Rect::new(
+            rect.x1 + half_width,
+            rect.y1,
+            half_width,
+            half_height,
+        ));
+        self.rects.push(Rect::new(
+            rect.x1 + half_width,
+            rect.y1 + half_height,
+            half_width,
+            half_height,
+        ));
+    }
+
+    fn get_random_rect(&self, rng: &mut RandomNumberGenerator) -> Rect {
+        let idx = rng.roll_dice(1, self.rects.len() as i32) - 1;
+        self.rects[idx as usize].clone()
+    }
+
+    /// Random room, which fits into the rect
+    fn get_random_sub_rect(rect: &Rect, rng: &mut RandomNumberGenerator) -> Rect {
+        let rect_width = i32::abs(rect.x1 - rect.x2);
+        let rect_height = i32::abs(rect.y1 - rect.y2);
+
+        let w = i32::max(3, rng.roll_dice(1, i32::min(rect_width, 10)) - 1) + 1;
+        let h = i32::max(3, rng.roll_dice(1, i32::min(rect_height, 10)) - 1) + 1;
+        let x = rect.x1 + rng.roll_dice(1, 6) - 1;
+        let y = rect.y1 + rng.roll_dice(1, 6) - 1;
+
+        Rect::new(x, y, w, h)
+    }
+
+    /// Rets true if the room (with a margin around it) is inside the level and touches only walls
+    fn is_possible(&self, room: &Rect) -> bool {
+        let expanded = Rect {
+            x1: room.x1 - 2,
+            x2: room.x2 + 2,
+            y1: room.y1 - 2,
+            y2: room.y2 + 2,
+        };
+
+        for y in expanded.y1..=expanded.y2 {
+            for x in expanded.x1..=expanded.x2 {
+                if x < 1 || x > self.level.width - 2 || y < 1 || y > self.level.height - 2 {
+                    return false;
+                }
+                let idx = self.level.xy_idx(x, y);
+                if self.level.tiles[idx] != TileType::Wall {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// L-shaped corridor between random points of both rooms
+    fn connect_rooms(&mut self, room: &Rect, next_room: &Rect, rng: &mut RandomNumberGenerator) {
+        let start_x = room.x1 + rng.roll_dice(1, i32::abs(room.x1 - room.x2));
+        let start_y = room.y1 + rng.roll_dice(1, i32::abs(room.y1 - room.y2));
+        let end_x = next_room.x1 + rng.roll_dice(1, i32::abs(next_room.x1 - next_room.x2));
+        let end_y = next_room.y1 + rng.roll_dice(1, i32::abs(next_room.y1 - next_room.y2));
+
+        apply_horiz_tunnel(&mut self.level, start_x, end_x, start_y);
+        apply_vert_tunnel(&mut self.level, start_y, end_y, end_x);
+    }
+}
+
+impl MapBuilder for BspDungeonBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.rects.clear();
+        let whole_map = Rect::new(2, 2, self.level.width - 5, self.level.height - 5);
+        self.add_subrects(&whole_map);
+
+        for _ in 0..NUM_ROOM_ATTEMPTS {
+            let rect = self.get_random_rect(rng);
+            let candidate = Self::get_random_sub_rect(&rect, rng);
+
+            if self.is_possible(&candidate) {
+                apply_room_to_level(&mut self.level, &candidate);
+                self.level.rooms.push(candidate);
+                self.add_subrects(&rect);
+            }
+        }
+
+        // Connecting rooms from left to right gives fewer crossing corridors
+        let mut rooms = std::mem::take(&mut self.level.rooms);
+        rooms.sort_by_key(|room| room.x1);
+        for pair in rooms.windows(2) {
+            self.connect_rooms(&pair[0], &pair[1], rng);
+        }
+        self.level.rooms = rooms;
+        ensure_a_room(&mut self.level);
+
+        let (stairs_x, stairs_y) = self.level.rooms[self.level.rooms.len() - 1].get_center();
+        let stairs_idx = self.level.xy_idx(stairs_x, stairs_y);
+        self.level.tiles[stairs_idx] = TileType::DownStairs;
+    }
+
+    fn get_level(&self) -> Level {
+        self.level.clone()
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        room_spawn_regions(&self.level)
+    }
+
+    fn get_player_start(&self) -> (i32, i32) {
+        self.level.rooms[0].get_center()
+    }
+}
@@ -0,0 +1,93 @@
+use rltk::RandomNumberGenerator;
+
+use super::common::{
+    chunked_spawn_regions, find_central_floor, remove_unreachable_areas_returning_most_distant,
+};
+use super::MapBuilder;
+use crate::level::{Level, TileType};
+
+/// Chance (in %) for a tile to start out as a wall
+const INITIAL_WALL_CHANCE: i32 = 55;
+const NUM_ITERATIONS: u8 = 15;
+
+/// Natural-looking caves: random noise smoothed out by repeatedly applying neighbour-count rules
+pub struct CellularAutomataBuilder {
+    level: Level,
+    player_start: (i32, i32),
+}
+
+impl CellularAutomataBuilder {
+    pub fn new(width: i32, height: i32, depth: i32) -> Self {
+        CellularAutomataBuilder {
+            level: Level::new(width, height, depth),
+            player_start: (0, 0),
+        }
+    }
+
+    fn count_wall_neighbours(&self, x: i32, y: i32) -> usize {
+        let mut walls = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx, dy) == (0, 0) {
+                    continue;
+                }
+                if self.level.tiles[self.level.xy_idx(x + dx, y + dy)] == TileType::Wall {
+                    walls += 1;
+                }
+            }
+        }
+        walls
+    }
+}
+
+impl MapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        let (width, height) = (self.level.width, self.level.height);
+
+        // Start with noise, keeping the outer border solid
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let idx = self.level.xy_idx(x, y);
+                self.level.tiles[idx] = if rng.roll_dice(1, 100) > INITIAL_WALL_CHANCE {
+                    TileType::Floor
+                } else {
+                    TileType::Wall
+                };
+            }
+        }
+
+        for _ in 0..NUM_ITERATIONS {
+            let mut new_tiles = self.level.tiles.clone();
+            for y in 1..height - 1 {
+                for x in 1..width - 1 {
+                    let walls = self.count_wall_neighbours(x, y);
+                    let idx = self.level.xy_idx(x, y);
+                    new_tiles[idx] = if walls > 4 || walls == 0 {
+                        TileType::Wall
+                    } else {
+                        TileType::Floor
+                    };
+                }
+            }
+            self.level.tiles = new_tiles;
+        }
+
+        self.player_start = find_central_floor(&mut self.level);
+        let start_idx = self.level.xy_idx(self.player_start.0, self.player_start.1);
+        let stairs_idx =
+            remove_unreachable_areas_returning_most_distant(&mut self.level, start_idx);
+        self.level.tiles[stairs_idx] = TileType::DownStairs;
+    }
+
+    fn get_level(&self) -> Level {
+        self.level.clone()
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        chunked_spawn_regions(&self.level, self.player_start)
+    }
+
+    fn get_player_start(&self) -> (i32, i32) {
+        self.player_start
+    }
+}
@@ -0,0 +1,149 @@
+use rltk::DijkstraMap;
+use std::cmp::{max, min};
+
+use crate::level::{Level, TileType};
+use crate::util::Rect;
+
+/// Side of the square chunks that open maps (caves) are split into for spawning
+const SPAWN_CHUNK_SIZE: i32 = 8;
+/// Cost of a diagonal step, the priciest one there is
+const MAX_STEP_COST: f32 = 1.45;
+/// Side of the room carved when a room builder couldn't place any
+const FALLBACK_ROOM_SIZE: i32 = 6;
+
+pub fn apply_room_to_level(level: &mut Level, room: &Rect) {
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            let tile_idx = level.xy_idx(x, y);
+            // Just a small self-testing piece of code
+            assert!(tile_idx < level.tiles.len());
+            level.tiles[tile_idx] = TileType::Floor;
+        }
+    }
+}
+
+/// Carves a room in the middle of the level if none could be placed, e.g. on small levels,
+/// so that room builders always have somewhere to put the player and the stairs
+pub fn ensure_a_room(level: &mut Level) {
+    if !level.rooms.is_empty() {
+        return;
+    }
+    let w = min(FALLBACK_ROOM_SIZE, level.width - 3);
+    let h = min(FALLBACK_ROOM_SIZE, level.height - 3);
+    let room = Rect::new((level.width - w) / 2 - 1, (level.height - h) / 2 - 1, w, h);
+    apply_room_to_level(level, &room);
+    level.rooms.push(room);
+}
+
+pub fn apply_horiz_tunnel(level: &mut Level, x1: i32, x2: i32, y: i32) {
+    for x in min(x1, x2)..=max(x1, x2) {
+        let idx = level.xy_idx(x, y);
+        if idx > 0 && idx < level.tiles.len() {
+            level.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_vert_tunnel(level: &mut Level, y1: i32, y2: i32, x: i32) {
+    for y in min(y1, y2)..=max(y1, y2) {
+        let idx = level.xy_idx(x, y);
+        if idx > 0 && idx < level.tiles.len() {
+            level.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+/// Floor tile indices of every room, except the first one (where the player starts)
+pub fn room_spawn_regions(level: &Level) -> Vec<Vec<usize>> {
+    level
+        .rooms
+        .iter()
+        .skip(1)
+        .map(|room| {
+            let mut region = Vec::new();
+            for y in room.y1 + 1..=room.y2 {
+                for x in room.x1 + 1..=room.x2 {
+                    region.push(level.xy_idx(x, y));
+                }
+            }
+            region
+        })
+        .collect()
+}
+
+/// Splits the floor into square chunks, skipping the one the player starts in
+pub fn chunked_spawn_regions(level: &Level, player_start: (i32, i32)) -> Vec<Vec<usize>> {
+    let chunks_per_row = (level.width + SPAWN_CHUNK_SIZE - 1) / SPAWN_CHUNK_SIZE;
+    let chunk_of =
+        |x: i32, y: i32| (y / SPAWN_CHUNK_SIZE * chunks_per_row + x / SPAWN_CHUNK_SIZE) as usize;
+    let start_chunk = chunk_of(player_start.0, player_start.1);
+
+    let chunk_count = chunk_of(level.width - 1, level.height - 1) + 1;
+    let mut regions: Vec<Vec<usize>> = vec![Vec::new(); chunk_count];
+    for (idx, tile) in level.tiles.iter().enumerate() {
+        if *tile != TileType::Floor {
+            continue;
+        }
+        let (x, y) = level.idx_xy(idx);
+        regions[chunk_of(x, y)].push(idx);
+    }
+
+    regions
+        .into_iter()
+        .enumerate()
+        .filter(|(chunk, region)| *chunk != start_chunk && !region.is_empty())
+        .map(|(_, region)| region)
+        .collect()
+}
+
+/// Walls off everything the player can't walk to from `start_idx`.
+/// Rets the index of the reachable tile which is the farthest away from the start.
+pub fn remove_unreachable_areas_returning_most_distant(
+    level: &mut Level,
+    start_idx: usize,
+) -> usize {
+    level.index_walls();
+    // No path visits a tile twice, so even one winding over the whole level stays below this
+    let max_depth = (level.width * level.height) as f32 * MAX_STEP_COST;
+    let dijkstra_map =
+        DijkstraMap::new(level.width, level.height, &[start_idx], &*level, max_depth);
+
+    let mut exit_tile = (start_idx, 0.0f32);
+    for (idx, tile) in level.tiles.iter_mut().enumerate() {
+        if *tile != TileType::Floor {
+            continue;
+        }
+        let distance = dijkstra_map.map[idx];
+        if distance == f32::MAX {
+            *tile = TileType::Wall;
+        } else if distance > exit_tile.1 {
+            exit_tile = (idx, distance);
+        }
+    }
+    level.index_walls();
+
+    exit_tile.0
+}
+
+/// Rets the floor tile closest to the middle of the level, for the player to start on.
+/// Carves the middle tile out if there's no floor at all.
+pub fn find_central_floor(level: &mut Level) -> (i32, i32) {
+    let (center_x, center_y) = (level.width / 2, level.height / 2);
+
+    let nearest_floor = level
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| **tile == TileType::Floor)
+        .map(|(idx, _)| level.idx_xy(idx))
+        .min_by_key(|(x, y)| (x - center_x).pow(2) + (y - center_y).pow(2));
+
+    match nearest_floor {
+        Some(pos) => pos,
+        None => {
+            let idx = level.xy_idx(center_x, center_y);
+            level.tiles[idx] = TileType::Floor;
+            (center_x, center_y)
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use rltk::RandomNumberGenerator;
+
+use super::common::{chunked_spawn_regions, remove_unreachable_areas_returning_most_distant};
+use super::MapBuilder;
+use crate::level::{Level, TileType};
+
+/// How many steps a single digger makes before it gives up
+const DRUNK_LIFETIME: u16 = 400;
+/// Digging goes on until this share of the level is floor
+const FLOOR_PERCENT: f32 = 0.5;
+
+/// Drunkard's walk: diggers stumble around randomly from the middle of the map, carving winding caves
+pub struct DrunkardsWalkBuilder {
+    level: Level,
+    player_start: (i32, i32),
+}
+
+impl DrunkardsWalkBuilder {
+    pub fn new(width: i32, height: i32, depth: i32) -> Self {
+        DrunkardsWalkBuilder {
+            level: Level::new(width, height, depth),
+            player_start: (0, 0),
+        }
+    }
+}
+
+impl MapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        let (width, height) = (self.level.width, self.level.height);
+        self.player_start = (width / 2, height / 2);
+        let start_idx = self.level.xy_idx(self.player_start.0, self.player_start.1);
+        self.level.tiles[start_idx] = TileType::Floor;
+
+        let total_tiles = (width * height) as usize;
+        let desired_floor_tiles = (FLOOR_PERCENT * total_tiles as f32) as usize;
+        let mut floor_tiles = vec![start_idx];
+
+        while floor_tiles.len() < desired_floor_tiles {
+            // Every digger starts on already dug out floor, so everything stays connected.
+            // Starting anywhere (not just in the middle) keeps big levels from taking forever.
+            let start = floor_tiles[rng.roll_dice(1, floor_tiles.len() as i32) as usize - 1];
+            let (mut x, mut y) = self.level.idx_xy(start);
+            for _ in 0..DRUNK_LIFETIME {
+                let idx = self.level.xy_idx(x, y);
+                if self.level.tiles[idx] == TileType::Wall {
+                    self.level.tiles[idx] = TileType::Floor;
+                    floor_tiles.push(idx);
+                }
+
+                match rng.roll_dice(1, 4) {
+                    1 if x > 2 => x -= 1,
+                    2 if x < width - 2 => x += 1,
+                    3 if y > 2 => y -= 1,
+                    4 if y < height - 2 => y += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        let stairs_idx =
+            remove_unreachable_areas_returning_most_distant(&mut self.level, start_idx);
+        self.level.tiles[stairs_idx] = TileType::DownStairs;
+    }
+
+    fn get_level(&self) -> Level {
+        self.level.clone()
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        chunked_spawn_regions(&self.level, self.player_start)
+    }
+
+    fn get_player_start(&self) -> (i32, i32) {
+        self.player_start
+    }
+}
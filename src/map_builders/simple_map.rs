@@ -0,0 +1,91 @@
+use rltk::RandomNumberGenerator;
+
+use super::common::{
+    apply_horiz_tunnel, apply_room_to_level, apply_vert_tunnel, ensure_a_room, room_spawn_regions,
+};
+use super::MapBuilder;
+use crate::level::{Level, TileType};
+use crate::util::Rect;
+
+const NUM_MAX_ROOMS: u8 = 30;
+const MIN_ROOM_SIZE: u8 = 6;
+const MAX_ROOM_SIZE: u8 = 10;
+
+/// Classic rooms and corridors: randomly placed rooms, each one connected to the previous one
+pub struct SimpleMapBuilder {
+    level: Level,
+}
+
+impl SimpleMapBuilder {
+    pub fn new(width: i32, height: i32, depth: i32) -> Self {
+        SimpleMapBuilder {
+            level: Level::new(width, height, depth),
+        }
+    }
+
+    fn add_corridors(&mut self, new_room: &Rect, rng: &mut RandomNumberGenerator) {
+        let (new_x, new_y) = new_room.get_center();
+        let (prev_x, prev_y) = self.level.rooms[self.level.rooms.len() - 1].get_center();
+        if rng.range(0, 2) == 1 {
+            apply_horiz_tunnel(&mut self.level, prev_x, new_x, prev_y);
+            apply_vert_tunnel(&mut self.level, prev_y, new_y, new_x);
+        } else {
+            apply_vert_tunnel(&mut self.level, prev_y, new_y, prev_x);
+            apply_horiz_tunnel(&mut self.level, prev_x, new_x, new_y);
+        }
+    }
+}
+
+impl MapBuilder for SimpleMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        let (width, height) = (self.level.width, self.level.height);
+
+        for _ in 0..NUM_MAX_ROOMS {
+            let w = rng.range(MIN_ROOM_SIZE, MAX_ROOM_SIZE) as i32;
+            let h = rng.range(MIN_ROOM_SIZE, MAX_ROOM_SIZE) as i32;
+            let x = rng.roll_dice(1, width - w - 1) - 1;
+            let y = rng.roll_dice(1, height - h - 1) - 1;
+            let new_room = Rect::new(x, y, w, h);
+
+            let no_intersections = !self
+                .level
+                .rooms
+                .iter()
+                .any(|room| new_room.intersects(room));
+
+            // TODO later: we could sometimes allow intersections
+            // to create more interesting rooms.
+            if !no_intersections {
+                continue;
+            }
+
+            apply_room_to_level(&mut self.level, &new_room);
+
+            // Corridorize
+            if !self.level.rooms.is_empty() {
+                self.add_corridors(&new_room, rng);
+            }
+
+            self.level.rooms.push(new_room);
+        }
+
+        ensure_a_room(&mut self.level);
+
+        // Way down is in the last room, as far from the player start as it gets
+        let (stairs_x, stairs_y) = self.level.rooms[self.level.rooms.len() - 1].get_center();
+        let stairs_idx = self.level.xy_idx(stairs_x, stairs_y);
+        self.level.tiles[stairs_idx] = TileType::DownStairs;
+    }
+
+    fn get_level(&self) -> Level {
+        self.level.clone()
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        room_spawn_regions(&self.level)
+    }
+
+    fn get_player_start(&self) -> (i32, i32) {
+        self.level.rooms[0].get_center()
+    }
+}
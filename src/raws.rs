@@ -0,0 +1,479 @@
+use rltk::{FontCharType, RGB};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::components::{
+    EquipmentSlot, IdleBehaviour, Reaction, StatusEffect, StatusKind, NORMAL_SPEED, PLAYER_FACTION,
+};
+use crate::util::{arg_or_env, RandomTable};
+
+/// Data file with monster and item templates, read at startup.
+/// Relative to wherever the game is installed, see raws_path.
+pub const RAWS_PATH: &str = "raws/spawns.json";
+const RAWS_ENV_VAR: &str = "ROGUELIKE_RAWS";
+
+/// Where to read the raws from: `--raws <path>` or ROGUELIKE_RAWS if given,
+/// else RAWS_PATH next to the executable, else RAWS_PATH in the working directory
+pub fn raws_path() -> PathBuf {
+    if let Some(path) = arg_or_env("raws", RAWS_ENV_VAR) {
+        return PathBuf::from(path);
+    }
+    env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(RAWS_PATH)))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(RAWS_PATH))
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawStats {
+    pub max_hp: u16,
+    pub defense: i32,
+    pub power: u16,
+}
+
+/// Bow (or whatever else it throws) of a monster that shoots, or of a worn ranged weapon
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawRanged {
+    pub range: i32,
+    pub power: u16,
+}
+
+/// Lasting effect an item puts on whoever it is used on,
+/// e.g. `{ "kind": "poison", "turns": 5, "damage": 2 }`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RawStatus {
+    Poison { turns: i32, damage: i32 },
+    Regeneration { turns: i32, heal: i32 },
+    Haste { turns: i32 },
+}
+
+impl RawStatus {
+    pub fn to_effect(&self) -> StatusEffect {
+        let (kind, turns) = match *self {
+            RawStatus::Poison { turns, damage } => (StatusKind::Poisoned { damage }, turns),
+            RawStatus::Regeneration { turns, heal } => (StatusKind::Regenerating { heal }, turns),
+            RawStatus::Haste { turns } => (StatusKind::Hasted, turns),
+        };
+        StatusEffect {
+            kind,
+            turns,
+            source: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MonsterRaw {
+    pub name: String,
+    pub glyph: String,
+    /// Html color, e.g. "#FF0000"
+    pub fg: String,
+    #[serde(default = "default_bg")]
+    pub bg: String,
+    pub stats: RawStats,
+    pub vision_range: i32,
+    /// Energy gained per tick, the player has NORMAL_SPEED
+    #[serde(default = "default_speed")]
+    pub speed: i32,
+    /// Adds a RangedAttacker comp, making the monster keep its distance and shoot
+    pub ranged: Option<RawRanged>,
+    /// Xp awarded for killing the monster
+    #[serde(default)]
+    pub xp: i32,
+    /// What the monster does until it notices someone hostile, "wander" if omitted
+    #[serde(default)]
+    pub idle: IdleBehaviour,
+    /// Name of a faction from the reaction table
+    pub faction: String,
+}
+
+/// Ally waiting somewhere in the dungeon for the player to tell it to follow.
+/// Always on the player's side, so there's no faction to pick.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CompanionRaw {
+    pub name: String,
+    pub glyph: String,
+    pub fg: String,
+    #[serde(default = "default_bg")]
+    pub bg: String,
+    pub stats: RawStats,
+    pub vision_range: i32,
+    #[serde(default = "default_speed")]
+    pub speed: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ItemRaw {
+    pub name: String,
+    pub glyph: String,
+    pub fg: String,
+    #[serde(default = "default_bg")]
+    pub bg: String,
+    #[serde(default)]
+    pub consumable: bool,
+    /// Adds a Healer comp with this heal amount
+    pub healing: Option<u32>,
+    /// Adds an InflictsDamage comp with this damage
+    pub damage: Option<i32>,
+    /// Adds a Confusion comp lasting this many turns
+    pub confusion: Option<i32>,
+    /// Adds a Ranged comp, making the item aimed at a tile instead of used on oneself
+    pub range: Option<i32>,
+    /// Adds an AreaOfEffect comp with this radius, only makes sense along with `range`
+    pub area_of_effect: Option<i32>,
+    /// Adds an AppliesStatus comp with this effect
+    pub status: Option<RawStatus>,
+    /// Adds an Equippable comp, making the item worn rather than used up
+    pub slot: Option<EquipmentSlot>,
+    /// Adds a MeleePowerBonus comp, only makes sense along with `slot`
+    pub power_bonus: Option<i32>,
+    /// Adds a DefenseBonus comp, only makes sense along with `slot`
+    pub defense_bonus: Option<i32>,
+    /// Adds a RangedWeapon comp, only makes sense along with `slot`
+    pub shoots: Option<RawRanged>,
+}
+
+/// Row of the reaction table: how the faction's members treat everyone else
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FactionRaw {
+    pub name: String,
+    /// Reaction to any faction not listed in `reactions`
+    pub default: Reaction,
+    #[serde(default)]
+    pub reactions: HashMap<String, Reaction>,
+}
+
+/// How likely a template is to be picked when populating a level at some depth
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnTableEntry {
+    /// Name of a monster or item template
+    pub name: String,
+    pub weight: i32,
+    pub min_depth: i32,
+    /// No upper limit if omitted
+    pub max_depth: Option<i32>,
+    /// Added to the weight for every level below min_depth, making the entry more common deeper down
+    #[serde(default)]
+    pub extra_weight_per_depth: i32,
+}
+
+impl SpawnTableEntry {
+    /// Rets 0 if the entry can't spawn at this depth
+    pub fn weight_at(&self, depth: i32) -> i32 {
+        let too_deep = self.max_depth.is_some_and(|max_depth| depth > max_depth);
+        if depth < self.min_depth || too_deep {
+            0
+        } else {
+            self.weight + self.extra_weight_per_depth * (depth - self.min_depth)
+        }
+    }
+}
+
+/// Every spawnable template, inserted into the ECS as a resource
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Raws {
+    pub monsters: Vec<MonsterRaw>,
+    pub companions: Vec<CompanionRaw>,
+    pub items: Vec<ItemRaw>,
+    pub spawn_table: Vec<SpawnTableEntry>,
+    pub factions: Vec<FactionRaw>,
+}
+
+fn default_bg() -> String {
+    "#000000".to_string()
+}
+
+fn default_speed() -> i32 {
+    NORMAL_SPEED
+}
+
+impl Raws {
+    pub fn find_monster(&self, name: &str) -> Option<&MonsterRaw> {
+        self.monsters.iter().find(|monster| monster.name == name)
+    }
+
+    pub fn find_companion(&self, name: &str) -> Option<&CompanionRaw> {
+        self.companions
+            .iter()
+            .find(|companion| companion.name == name)
+    }
+
+    pub fn find_item(&self, name: &str) -> Option<&ItemRaw> {
+        self.items.iter().find(|item| item.name == name)
+    }
+
+    pub fn find_faction(&self, name: &str) -> Option<&FactionRaw> {
+        self.factions.iter().find(|faction| faction.name == name)
+    }
+
+    /// How members of the `from` faction treat those of the `to` faction.
+    /// Members of one faction are always friendly to each other.
+    pub fn reaction(&self, from: &str, to: &str) -> Reaction {
+        if from == to {
+            return Reaction::Friendly;
+        }
+        self.find_faction(from)
+            .map_or(Reaction::Neutral, |faction| {
+                faction
+                    .reactions
+                    .get(to)
+                    .copied()
+                    .unwrap_or(faction.default)
+            })
+    }
+
+    /// Monsters, and the companions waiting among them, which can spawn at the given depth,
+    /// weighted for that depth
+    pub fn monster_table(&self, depth: i32) -> RandomTable {
+        self.table_for_depth(depth, |name| {
+            self.find_monster(name).is_some() || self.find_companion(name).is_some()
+        })
+    }
+
+    /// Items which can spawn at the given depth, weighted for that depth
+    pub fn item_table(&self, depth: i32) -> RandomTable {
+        self.table_for_depth(depth, |name| self.find_item(name).is_some())
+    }
+
+    fn table_for_depth<F: Fn(&str) -> bool>(&self, depth: i32, include: F) -> RandomTable {
+        self.spawn_table
+            .iter()
+            .filter(|entry| include(&entry.name))
+            .fold(RandomTable::new(), |table, entry| {
+                table.add(&entry.name, entry.weight_at(depth))
+            })
+    }
+
+    /// Checks everything serde can't, so that bad data is reported at startup rather than mid-run
+    fn validate(&self) -> Result<(), String> {
+        let mut faction_names = HashSet::new();
+        for (i, faction) in self.factions.iter().enumerate() {
+            let entry = format!("factions[{}] ({:?})", i, faction.name);
+            if faction.name.trim().is_empty() {
+                return Err(format!("{}: name must not be empty", entry));
+            }
+            if !faction_names.insert(faction.name.as_str()) {
+                return Err(format!("{}: name is already taken", entry));
+            }
+        }
+        for (i, faction) in self.factions.iter().enumerate() {
+            let entry = format!("factions[{}] ({:?})", i, faction.name);
+            if let Some(other) = faction
+                .reactions
+                .keys()
+                .find(|other| !faction_names.contains(other.as_str()))
+            {
+                return Err(format!("{}: there is no faction named {:?}", entry, other));
+            }
+        }
+        if !faction_names.contains(PLAYER_FACTION) {
+            return Err(format!(
+                "factions: the {:?} faction is missing",
+                PLAYER_FACTION
+            ));
+        }
+
+        let mut names = HashSet::new();
+
+        for (i, monster) in self.monsters.iter().enumerate() {
+            let entry = format!("monsters[{}] ({:?})", i, monster.name);
+            validate_common(
+                &entry,
+                &monster.name,
+                &monster.glyph,
+                &monster.fg,
+                &monster.bg,
+            )?;
+            if monster.stats.max_hp == 0 {
+                return Err(format!("{}: stats.max_hp must be above 0", entry));
+            }
+            if monster.vision_range < 1 {
+                return Err(format!("{}: vision_range must be above 0", entry));
+            }
+            if monster.xp < 0 {
+                return Err(format!("{}: xp must not be negative", entry));
+            }
+            if !faction_names.contains(monster.faction.as_str()) {
+                return Err(format!(
+                    "{}: there is no faction named {:?}",
+                    entry, monster.faction
+                ));
+            }
+            if monster.speed < 1 {
+                return Err(format!("{}: speed must be above 0", entry));
+            }
+            if monster
+                .ranged
+                .as_ref()
+                .is_some_and(|ranged| ranged.range < 2)
+            {
+                return Err(format!("{}: ranged.range must be at least 2", entry));
+            }
+            if !names.insert(monster.name.as_str()) {
+                return Err(format!("{}: name is already taken", entry));
+            }
+        }
+
+        for (i, companion) in self.companions.iter().enumerate() {
+            let entry = format!("companions[{}] ({:?})", i, companion.name);
+            validate_common(
+                &entry,
+                &companion.name,
+                &companion.glyph,
+                &companion.fg,
+                &companion.bg,
+            )?;
+            if companion.stats.max_hp == 0 {
+                return Err(format!("{}: stats.max_hp must be above 0", entry));
+            }
+            if companion.vision_range < 1 {
+                return Err(format!("{}: vision_range must be above 0", entry));
+            }
+            if companion.speed < 1 {
+                return Err(format!("{}: speed must be above 0", entry));
+            }
+            if !names.insert(companion.name.as_str()) {
+                return Err(format!("{}: name is already taken", entry));
+            }
+        }
+
+        for (i, item) in self.items.iter().enumerate() {
+            let entry = format!("items[{}] ({:?})", i, item.name);
+            validate_common(&entry, &item.name, &item.glyph, &item.fg, &item.bg)?;
+            if item.damage.is_some_and(|damage| damage < 1) {
+                return Err(format!("{}: damage must be above 0", entry));
+            }
+            if item.confusion.is_some_and(|turns| turns < 1) {
+                return Err(format!("{}: confusion must be above 0", entry));
+            }
+            if item.range.is_some_and(|range| range < 1) {
+                return Err(format!("{}: range must be above 0", entry));
+            }
+            if item.area_of_effect.is_some_and(|radius| radius < 1) {
+                return Err(format!("{}: area_of_effect must be above 0", entry));
+            }
+            if item.area_of_effect.is_some() && item.range.is_none() {
+                return Err(format!("{}: area_of_effect needs a range", entry));
+            }
+            if let Some(status) = &item.status {
+                let effect = status.to_effect();
+                let strength = match effect.kind {
+                    StatusKind::Poisoned { damage } => damage,
+                    StatusKind::Regenerating { heal } => heal,
+                    StatusKind::Confused | StatusKind::Hasted => 1,
+                };
+                if effect.turns < 1 || strength < 1 {
+                    return Err(format!("{}: status values must be above 0", entry));
+                }
+            }
+            if (item.power_bonus.is_some() || item.defense_bonus.is_some() || item.shoots.is_some())
+                && item.slot.is_none()
+            {
+                return Err(format!(
+                    "{}: power_bonus, defense_bonus and shoots need a slot",
+                    entry
+                ));
+            }
+            if item.shoots.as_ref().is_some_and(|shoots| shoots.range < 2) {
+                return Err(format!("{}: shoots.range must be at least 2", entry));
+            }
+            if item.slot.is_some() && (item.consumable || item.range.is_some()) {
+                return Err(format!(
+                    "{}: worn items can't be consumable or ranged",
+                    entry
+                ));
+            }
+            // Otherwise the user would hurt themselves
+            if (item.damage.is_some() || item.confusion.is_some()) && item.range.is_none() {
+                return Err(format!("{}: damage and confusion need a range", entry));
+            }
+            if !names.insert(item.name.as_str()) {
+                return Err(format!("{}: name is already taken", entry));
+            }
+        }
+
+        for (i, entry) in self.spawn_table.iter().enumerate() {
+            let entry_name = format!("spawn_table[{}] ({:?})", i, entry.name);
+            if self.find_monster(&entry.name).is_none()
+                && self.find_companion(&entry.name).is_none()
+                && self.find_item(&entry.name).is_none()
+            {
+                return Err(format!(
+                    "{}: there is no monster, companion or item with this name",
+                    entry_name
+                ));
+            }
+            if entry.weight < 1 {
+                return Err(format!("{}: weight must be above 0", entry_name));
+            }
+            if entry.min_depth < 1 {
+                return Err(format!("{}: min_depth must be above 0", entry_name));
+            }
+            if entry
+                .max_depth
+                .is_some_and(|max_depth| max_depth < entry.min_depth)
+            {
+                return Err(format!(
+                    "{}: max_depth must not be below min_depth",
+                    entry_name
+                ));
+            }
+            if entry.extra_weight_per_depth < 0 {
+                return Err(format!(
+                    "{}: extra_weight_per_depth must not be negative",
+                    entry_name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_common(entry: &str, name: &str, glyph: &str, fg: &str, bg: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err(format!("{}: name must not be empty", entry));
+    }
+    if glyph.chars().count() != 1 {
+        return Err(format!(
+            "{}: glyph must be a single character, got {:?}",
+            entry, glyph
+        ));
+    }
+    parse_color(fg).map_err(|err| format!("{}: fg {}", entry, err))?;
+    parse_color(bg).map_err(|err| format!("{}: bg {}", entry, err))?;
+    Ok(())
+}
+
+pub fn parse_color(code: &str) -> Result<RGB, String> {
+    RGB::from_hex(code).map_err(|err| format!("{:?} is not a valid color ({:?})", code, err))
+}
+
+/// Glyph strings are validated on load, so this can't fail on loaded raws
+pub fn parse_glyph(glyph: &str) -> FontCharType {
+    rltk::to_cp437(glyph.chars().next().unwrap_or('?'))
+}
+
+/// Reads and validates the raws. Errors name the file and the offending entry.
+pub fn load_raws(path: impl AsRef<Path>) -> Result<Raws, String> {
+    let path = path.as_ref();
+    let data = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let raws: Raws =
+        serde_json::from_str(&data).map_err(|err| format!("{}: {}", path.display(), err))?;
+    raws.validate()
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(raws)
+}
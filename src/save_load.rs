@@ -0,0 +1,265 @@
+use rltk::{Point, RandomNumberGenerator};
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{
+    DeserializeComponents, MarkedBuilder, SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
+};
+use specs_derive::Component;
+use std::fs::{self, File};
+use std::path::Path;
+
+use crate::components::{
+    AppliesStatus, AreaOfEffect, CombatStats, Companion, CompanionTarget, Confusion, Consumable,
+    DefenseBonus, Equippable, Equipped, Experience, Faction, GameplayName, Healer, InBackpack,
+    InflictsDamage, Initiative, Item, LeftMover, MeleeAttackIntent, MeleePowerBonus, MonsterAI,
+    MonsterChar, MoveIntent, MyTurn, PlayerChar, Position, Ranged, RangedAttackIntent,
+    RangedAttacker, RangedWeapon, Renderable, StatusEffects, TileBlocker, Viewshed, WantsToDrop,
+    WantsToEquip, WantsToPickUp, WantsToUnequip, WantsToUse, XpReward,
+};
+use crate::game_log::GameLog;
+use crate::level::Level;
+use crate::seed::RunSeed;
+
+const SAVE_PATH: &str = "./savegame.json";
+
+/// Marker for the entities that get written into the save file
+pub struct SerializeMe;
+
+/// Resources don't take part in component serialization,
+/// so they are carried by a temporary entity with this comp
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct SerializationHelper {
+    pub level: Level,
+    pub log: GameLog,
+    pub rng: RandomNumberGenerator,
+    pub seed: RunSeed,
+}
+
+macro_rules! serialize_individually {
+    ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        SerializeComponents::<NoError, SimpleMarker<SerializeMe>>::serialize(
+            &( $ecs.read_storage::<$type>(), ),
+            &$data.0,
+            &$data.1,
+            &mut $ser,
+        )
+        .map_err(|err| format!("Can't write {} into the save: {}", stringify!($type), err))?;
+        )*
+    };
+}
+
+macro_rules! deserialize_individually {
+    ($ecs:expr, $de:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        DeserializeComponents::<NoError, _>::deserialize(
+            &mut ( &mut $ecs.write_storage::<$type>(), ),
+            &$data.0,
+            &mut $data.1,
+            &mut $data.2,
+            &mut $de,
+        )
+        .map_err(|err| format!("Can't read {} from the save: {}", stringify!($type), err))?;
+        )*
+    };
+}
+
+pub fn does_save_exist() -> bool {
+    Path::new(SAVE_PATH).exists()
+}
+
+pub fn delete_save() {
+    if does_save_exist() {
+        fs::remove_file(SAVE_PATH).expect("Should be able to delete the save file");
+    }
+}
+
+/// Writes every marked entity, along with the level, game log, rng and seed, into the save file
+pub fn save_game(ecs: &mut World) -> Result<(), String> {
+    save_game_to(ecs, SAVE_PATH)
+}
+
+/// Same as save_game, but into the given file instead of the usual save
+pub fn save_game_to(ecs: &mut World, path: &str) -> Result<(), String> {
+    puffin::profile_function!();
+    let writer = File::create(path).map_err(|err| format!("Can't write {}: {}", path, err))?;
+
+    let helper = SerializationHelper {
+        level: (*ecs.fetch::<Level>()).clone(),
+        log: (*ecs.fetch::<GameLog>()).clone(),
+        rng: (*ecs.fetch::<RandomNumberGenerator>()).clone(),
+        seed: *ecs.fetch::<RunSeed>(),
+    };
+    let helper_ent = ecs
+        .create_entity()
+        .with(helper)
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    // The helper has to go whether the writing worked or not
+    let written = (|| -> Result<(), String> {
+        let data = (
+            ecs.entities(),
+            ecs.read_storage::<SimpleMarker<SerializeMe>>(),
+        );
+        let mut serializer = serde_json::Serializer::new(writer);
+        serialize_individually!(
+            ecs,
+            serializer,
+            data,
+            Position,
+            Renderable,
+            LeftMover,
+            PlayerChar,
+            Viewshed,
+            MonsterChar,
+            GameplayName,
+            TileBlocker,
+            CombatStats,
+            MeleeAttackIntent,
+            MoveIntent,
+            Item,
+            Healer,
+            Consumable,
+            InBackpack,
+            WantsToPickUp,
+            WantsToDrop,
+            WantsToUse,
+            Initiative,
+            MyTurn,
+            RangedAttacker,
+            RangedAttackIntent,
+            InflictsDamage,
+            Ranged,
+            AreaOfEffect,
+            Confusion,
+            StatusEffects,
+            AppliesStatus,
+            Equippable,
+            Equipped,
+            MeleePowerBonus,
+            DefenseBonus,
+            RangedWeapon,
+            WantsToEquip,
+            WantsToUnequip,
+            Experience,
+            XpReward,
+            MonsterAI,
+            Faction,
+            Companion,
+            CompanionTarget,
+            SerializationHelper
+        );
+        Ok(())
+    })();
+
+    ecs.delete_entity(helper_ent)
+        .expect("Should be able to delete the serialization helper after saving");
+    written
+}
+
+/// Replaces the whole world with the contents of the save file.
+/// On error the world is left half-loaded, so start a new game over it.
+pub fn load_game(ecs: &mut World) -> Result<(), String> {
+    load_game_from(ecs, SAVE_PATH)
+}
+
+/// Same as load_game, but from the given file instead of the usual save
+pub fn load_game_from(ecs: &mut World, path: &str) -> Result<(), String> {
+    puffin::profile_function!();
+    let save_data =
+        fs::read_to_string(path).map_err(|err| format!("Can't read {}: {}", path, err))?;
+    ecs.delete_all();
+    let mut de = serde_json::Deserializer::from_str(&save_data);
+
+    {
+        let mut data = (
+            &mut ecs.entities(),
+            &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(),
+            &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
+        );
+        deserialize_individually!(
+            ecs,
+            de,
+            data,
+            Position,
+            Renderable,
+            LeftMover,
+            PlayerChar,
+            Viewshed,
+            MonsterChar,
+            GameplayName,
+            TileBlocker,
+            CombatStats,
+            MeleeAttackIntent,
+            MoveIntent,
+            Item,
+            Healer,
+            Consumable,
+            InBackpack,
+            WantsToPickUp,
+            WantsToDrop,
+            WantsToUse,
+            Initiative,
+            MyTurn,
+            RangedAttacker,
+            RangedAttackIntent,
+            InflictsDamage,
+            Ranged,
+            AreaOfEffect,
+            Confusion,
+            StatusEffects,
+            AppliesStatus,
+            Equippable,
+            Equipped,
+            MeleePowerBonus,
+            DefenseBonus,
+            RangedWeapon,
+            WantsToEquip,
+            WantsToUnequip,
+            Experience,
+            XpReward,
+            MonsterAI,
+            Faction,
+            Companion,
+            CompanionTarget,
+            SerializationHelper
+        );
+    }
+
+    // Unpack the helper back into resources
+    let (helper_ent, helper) = {
+        let entities = ecs.entities();
+        let helpers = ecs.read_storage::<SerializationHelper>();
+        let (ent, helper) = (&entities, &helpers)
+            .join()
+            .next()
+            .ok_or("The save has no level in it")?;
+        (ent, helper.clone())
+    };
+    let mut level = helper.level;
+    level.tile_content = vec![Vec::new(); level.tiles.len()];
+    level.index_walls();
+    ecs.insert(level);
+    ecs.insert(helper.log);
+    ecs.insert(helper.rng);
+    ecs.insert(helper.seed);
+    ecs.delete_entity(helper_ent)
+        .expect("Should be able to delete the serialization helper after loading");
+
+    // Restore the player resources
+    let (player_ent, player_pos) = {
+        let entities = ecs.entities();
+        let players = ecs.read_storage::<PlayerChar>();
+        let positions = ecs.read_storage::<Position>();
+        let (ent, _, pos) = (&entities, &players, &positions)
+            .join()
+            .next()
+            .ok_or("The save has no player in it")?;
+        (ent, Point::new(pos.x, pos.y))
+    };
+    ecs.insert(player_ent);
+    ecs.insert(player_pos);
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use rltk::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+use crate::util::arg_or_env;
+
+/// Env var that can be used instead of the `--seed` CLI arg
+const SEED_ENV_VAR: &str = "ROGUELIKE_SEED";
+
+/// Seed the whole run is generated from: maps, spawns and combat rolls.
+/// Same seed and same player inputs reproduce the same run.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct RunSeed(pub u64);
+
+impl RunSeed {
+    /// Seed requested with `--seed <n>` (or ROGUELIKE_SEED), otherwise a random one
+    pub fn from_args_or_env() -> Self {
+        let requested = arg_or_env("seed", SEED_ENV_VAR).and_then(|value| parse_seed(&value));
+        RunSeed(requested.unwrap_or_else(|| RandomNumberGenerator::new().rand()))
+    }
+
+    pub fn rng(&self) -> RandomNumberGenerator {
+        RandomNumberGenerator::seeded(self.0)
+    }
+}
+
+fn parse_seed(value: &str) -> Option<u64> {
+    match value.trim().parse() {
+        Ok(seed) => Some(seed),
+        Err(_) => {
+            eprintln!("Ignoring seed {:?}, it has to be a number", value);
+            None
+        }
+    }
+}
@@ -1,13 +1,18 @@
 use rltk::{RandomNumberGenerator, RGB};
 use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
 
 use crate::{
     components::{
-        CombatStats, GameplayName, Healer, Item, MonsterChar, PlayerChar, Position, Renderable,
-        TileBlocker, Viewshed,
+        AppliesStatus, AreaOfEffect, CombatStats, Companion, CompanionOrder, Confusion, Consumable,
+        DefenseBonus, Equippable, Experience, Faction, GameplayName, Healer, InflictsDamage,
+        Initiative, Item, MeleePowerBonus, MonsterAI, MonsterChar, PlayerChar, Position, Ranged,
+        RangedAttacker, RangedWeapon, Renderable, TileBlocker, Viewshed, XpReward, ACTION_COST,
+        NORMAL_SPEED, PLAYER_FACTION,
     },
-    level::MAP_WIDTH_PIX,
-    util::Rect,
+    level::Level,
+    raws::{parse_color, parse_glyph, CompanionRaw, ItemRaw, MonsterRaw, Raws},
+    save_load::SerializeMe,
 };
 
 const MAX_NUM_MONSTERS_PER_ROOM: i32 = 2;
@@ -25,6 +30,7 @@ pub fn spawn_player(ecs: &mut World, player_pos: (i32, i32)) -> Entity {
             bg: RGB::named(rltk::BLACK),
         })
         .with(PlayerChar {})
+        .with(TileBlocker {})
         .with(Viewshed::new())
         .with(GameplayName {
             name: "Player".to_string(),
@@ -35,100 +41,252 @@ pub fn spawn_player(ecs: &mut World, player_pos: (i32, i32)) -> Entity {
             defense: 2,
             power: 5,
         })
+        .with(Initiative::new(NORMAL_SPEED))
+        .with(Experience::default())
+        .with(Faction {
+            name: PLAYER_FACTION.to_string(),
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
         .build()
 }
 
-pub fn spawn_rand_monster(ecs: &mut World, pos: (i32, i32)) {
-    let roll = {
+/// Rolls a monster from the depth's spawn table
+pub fn spawn_rand_monster(ecs: &mut World, pos: (i32, i32), depth: i32) {
+    let name = {
+        let table = ecs.fetch::<Raws>().monster_table(depth);
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        rng.roll_dice(1, 2)
+        table.roll(&mut rng)
     };
-    match roll {
-        1 => spawn_orc(ecs, pos),
-        _ => spawn_goblin(ecs, pos),
+    if let Some(name) = name {
+        spawn_named(ecs, &name, pos);
+    }
+}
+
+/// Rolls an item from the depth's spawn table
+pub fn spawn_rand_item(ecs: &mut World, pos: (i32, i32), depth: i32) {
+    let name = {
+        let table = ecs.fetch::<Raws>().item_table(depth);
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        table.roll(&mut rng)
     };
+    if let Some(name) = name {
+        spawn_named(ecs, &name, pos);
+    }
 }
 
-fn spawn_orc(ecs: &mut World, pos: (i32, i32)) {
-    spawn_monster(ecs, pos, rltk::to_cp437('o'), "Orc");
+/// Spawns a monster, a companion or an item from the raws by its name.
+/// Rets None if there's no such template.
+pub fn spawn_named(ecs: &mut World, name: &str, pos: (i32, i32)) -> Option<Entity> {
+    // Clone the template out, so that the raws aren't borrowed while building the entity
+    let (monster, companion, item) = {
+        let raws = ecs.fetch::<Raws>();
+        (
+            raws.find_monster(name).cloned(),
+            raws.find_companion(name).cloned(),
+            raws.find_item(name).cloned(),
+        )
+    };
+
+    if let Some(monster) = monster {
+        Some(spawn_monster(ecs, pos, &monster))
+    } else if let Some(companion) = companion {
+        Some(spawn_companion(ecs, pos, &companion))
+    } else if let Some(item) = item {
+        Some(spawn_item(ecs, pos, &item))
+    } else {
+        eprintln!("No monster, companion or item named {:?} in the raws", name);
+        None
+    }
 }
-fn spawn_goblin(ecs: &mut World, pos: (i32, i32)) {
-    spawn_monster(ecs, pos, rltk::to_cp437('g'), "Goblin");
+
+fn spawn_monster(ecs: &mut World, pos: (i32, i32), raw: &MonsterRaw) -> Entity {
+    let mut viewshed = Viewshed::new();
+    viewshed.range = raw.vision_range;
+
+    // Random head start, so that monsters of the same speed don't all move in lockstep
+    let mut initiative = Initiative::new(raw.speed);
+    initiative.energy = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .range(0, ACTION_COST);
+
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x: pos.0, y: pos.1 })
+        .with(raw_renderable(&raw.glyph, &raw.fg, &raw.bg))
+        .with(viewshed)
+        .with(MonsterChar {})
+        .with(GameplayName {
+            name: raw.name.clone(),
+        })
+        .with(TileBlocker {})
+        .with(CombatStats {
+            max_hp: raw.stats.max_hp,
+            hp: raw.stats.max_hp as i32,
+            defense: raw.stats.defense,
+            power: raw.stats.power,
+        })
+        .with(initiative)
+        .with(XpReward { xp: raw.xp })
+        .with(MonsterAI::new(raw.idle))
+        .with(Faction {
+            name: raw.faction.clone(),
+        });
+
+    if let Some(ranged) = &raw.ranged {
+        builder = builder.with(RangedAttacker {
+            range: ranged.range,
+            power: ranged.power,
+        });
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build()
 }
 
-fn spawn_monster<S: ToString>(
-    ecs: &mut World,
-    pos: (i32, i32),
-    glyph: rltk::FontCharType,
-    name: S,
-) {
+/// Companions are found waiting, until the player comes by and tells them to follow
+fn spawn_companion(ecs: &mut World, pos: (i32, i32), raw: &CompanionRaw) -> Entity {
+    let mut viewshed = Viewshed::new();
+    viewshed.range = raw.vision_range;
+
+    let mut initiative = Initiative::new(raw.speed);
+    initiative.energy = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .range(0, ACTION_COST);
+
     ecs.create_entity()
         .with(Position { x: pos.0, y: pos.1 })
-        .with(Renderable {
-            glyph,
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Viewshed::new())
-        .with(MonsterChar {})
+        .with(raw_renderable(&raw.glyph, &raw.fg, &raw.bg))
+        .with(viewshed)
         .with(GameplayName {
-            name: name.to_string(),
+            name: raw.name.clone(),
         })
         .with(TileBlocker {})
         .with(CombatStats {
-            max_hp: 16,
-            hp: 16,
-            defense: 1,
-            power: 4,
+            max_hp: raw.stats.max_hp,
+            hp: raw.stats.max_hp as i32,
+            defense: raw.stats.defense,
+            power: raw.stats.power,
+        })
+        .with(initiative)
+        // Gets stronger from its kills, same as the player
+        .with(Experience::default())
+        .with(Faction {
+            name: PLAYER_FACTION.to_string(),
+        })
+        .with(Companion {
+            order: CompanionOrder::Stay,
+            recruited: false,
         })
-        .build();
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn spawn_item(ecs: &mut World, pos: (i32, i32), raw: &ItemRaw) -> Entity {
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x: pos.0, y: pos.1 })
+        .with(raw_renderable(&raw.glyph, &raw.fg, &raw.bg))
+        .with(GameplayName {
+            name: raw.name.clone(),
+        })
+        .with(Item {});
+
+    if raw.consumable {
+        builder = builder.with(Consumable {});
+    }
+    if let Some(heal_amount) = raw.healing {
+        builder = builder.with(Healer { heal_amount });
+    }
+    if let Some(damage) = raw.damage {
+        builder = builder.with(InflictsDamage { damage });
+    }
+    if let Some(turns) = raw.confusion {
+        builder = builder.with(Confusion { turns });
+    }
+    if let Some(range) = raw.range {
+        builder = builder.with(Ranged { range });
+    }
+    if let Some(radius) = raw.area_of_effect {
+        builder = builder.with(AreaOfEffect { radius });
+    }
+    if let Some(slot) = raw.slot {
+        builder = builder.with(Equippable { slot });
+    }
+    if let Some(power) = raw.power_bonus {
+        builder = builder.with(MeleePowerBonus { power });
+    }
+    if let Some(defense) = raw.defense_bonus {
+        builder = builder.with(DefenseBonus { defense });
+    }
+    if let Some(shoots) = &raw.shoots {
+        builder = builder.with(RangedWeapon {
+            range: shoots.range,
+            power: shoots.power,
+        });
+    }
+    if let Some(status) = &raw.status {
+        builder = builder.with(AppliesStatus {
+            effect: status.to_effect(),
+        });
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build()
+}
+
+/// Raws are validated on load, so the colors are known to be fine here
+fn raw_renderable(glyph: &str, fg: &str, bg: &str) -> Renderable {
+    Renderable {
+        glyph: parse_glyph(glyph),
+        fg: parse_color(fg).unwrap_or_else(|_| RGB::named(rltk::WHITE)),
+        bg: parse_color(bg).unwrap_or_else(|_| RGB::named(rltk::BLACK)),
+    }
 }
 
-pub fn spawn_room_content(ecs: &mut World, room: &Rect) {
+/// Populates a spawn region (a group of floor tile indices) with monsters and items.
+/// Deeper levels get more monsters, rolled from the depth's spawn table.
+pub fn spawn_region(ecs: &mut World, region: &[usize], depth: i32) {
     let mut monster_spawn_idx: Vec<usize> = Vec::new();
     let mut item_spawn_idx: Vec<usize> = Vec::new();
 
     // Keeping the borrow checker happy by scoping stuff
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        let num_monsters = rng.roll_dice(1, MAX_NUM_MONSTERS_PER_ROOM + 2) - 3;
+        let num_monsters = rng.roll_dice(1, MAX_NUM_MONSTERS_PER_ROOM + 2) - 3 + (depth - 1) / 2;
         let num_items = rng.roll_dice(1, MAX_NUM_ITEMS_PER_ROOM + 2) - 3;
 
+        // Small regions can't fit everything
+        let num_monsters = i32::min(num_monsters, region.len() as i32);
+        let num_items = i32::min(num_items, region.len() as i32);
+
         for _ in 0..num_monsters {
-            let new_idx = find_new_monster_idx(room, &mut rng, &monster_spawn_idx);
+            let new_idx = find_new_monster_idx(region, &mut rng, &monster_spawn_idx);
             monster_spawn_idx.push(new_idx);
         }
 
         for _ in 0..num_items {
-            let new_idx = find_new_item_idx(room, &mut rng, &item_spawn_idx);
+            let new_idx = find_new_item_idx(region, &mut rng, &item_spawn_idx);
             item_spawn_idx.push(new_idx);
         }
     }
 
     // Actually spawn the monsters
     for idx in monster_spawn_idx.iter() {
-        let x = *idx % MAP_WIDTH_PIX;
-        let y = *idx / MAP_WIDTH_PIX;
-        spawn_rand_monster(ecs, (x as i32, y as i32));
+        let pos = ecs.fetch::<Level>().idx_xy(*idx);
+        spawn_rand_monster(ecs, pos, depth);
     }
-    // Actually spawn the items (pots)
+    // Actually spawn the items
     for idx in item_spawn_idx.iter() {
-        let x = *idx % MAP_WIDTH_PIX;
-        let y = *idx / MAP_WIDTH_PIX;
-        spawn_health_potion(ecs, x as i32, y as i32);
+        let pos = ecs.fetch::<Level>().idx_xy(*idx);
+        spawn_rand_item(ecs, pos, depth);
     }
 }
 
 fn find_new_monster_idx(
-    room: &Rect,
+    region: &[usize],
     rng: &mut specs::shred::FetchMut<RandomNumberGenerator>,
-    monster_spawn_idx: &Vec<usize>,
+    monster_spawn_idx: &[usize],
 ) -> usize {
     loop {
-        let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-        let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-
-        let idx = (y * MAP_WIDTH_PIX) + x;
+        let idx = region[rng.roll_dice(1, region.len() as i32) as usize - 1];
         if !monster_spawn_idx.contains(&idx) {
             return idx;
         }
@@ -136,32 +294,14 @@ fn find_new_monster_idx(
 }
 
 fn find_new_item_idx(
-    room: &Rect,
+    region: &[usize],
     rng: &mut specs::shred::FetchMut<RandomNumberGenerator>,
-    item_spawn_idx: &Vec<usize>,
+    item_spawn_idx: &[usize],
 ) -> usize {
     loop {
-        let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-        let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-        let idx = (y * MAP_WIDTH_PIX) + x;
+        let idx = region[rng.roll_dice(1, region.len() as i32) as usize - 1];
         if !item_spawn_idx.contains(&idx) {
             return idx;
         }
     }
 }
-
-fn spawn_health_potion(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('¡'),
-            fg: RGB::named(rltk::MAGENTA),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(GameplayName {
-            name: "Health Potion".to_string(),
-        })
-        .with(Item {})
-        .with(Healer { heal_amount: 8 })
-        .build();
-}
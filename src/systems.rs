@@ -1,28 +1,47 @@
 use specs::prelude::*;
 
+pub mod companion_ai_system;
 pub mod damage_system;
+pub mod distance_map_system;
+pub mod initiative_system;
+pub mod inventory_system;
 pub mod map_indexing_system;
 pub mod melee_combat_system;
 pub mod monster_ai_system;
+pub mod movement_system;
+pub mod ranged_combat_system;
+pub mod status_effect_system;
 pub mod visibility_system;
+pub use companion_ai_system::*;
 pub use damage_system::*;
+pub use distance_map_system::*;
+pub use initiative_system::*;
+pub use inventory_system::*;
 pub use map_indexing_system::*;
 pub use melee_combat_system::*;
 pub use monster_ai_system::*;
+pub use movement_system::*;
+pub use ranged_combat_system::*;
+pub use status_effect_system::*;
 pub use visibility_system::*;
 
 use crate::components::{LeftMover, Position};
+use crate::level::Level;
 
 // Quick example of a (useless) system that moves LeftMovers to the left
 pub struct _LeftMoverSystem {}
 impl<'a> System<'a> for _LeftMoverSystem {
-    type SystemData = (ReadStorage<'a, LeftMover>, WriteStorage<'a, Position>);
+    type SystemData = (
+        ReadExpect<'a, Level>,
+        ReadStorage<'a, LeftMover>,
+        WriteStorage<'a, Position>,
+    );
 
-    fn run(&mut self, (lefty, mut pos): Self::SystemData) {
+    fn run(&mut self, (level, lefty, mut pos): Self::SystemData) {
         for (_, pos) in (&lefty, &mut pos).join() {
             pos.x -= 1;
             if pos.x < 0 {
-                pos.x = 79;
+                pos.x = level.width - 1;
             }
         }
     }
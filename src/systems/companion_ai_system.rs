@@ -0,0 +1,185 @@
+use super::monster_ai_system::{
+    move_to, nearest_hostile, path_step_next_to, random_step, step_by_distance,
+};
+use crate::{
+    components::{
+        CombatStats, Companion, CompanionOrder, CompanionTarget, Faction, GameplayName, Initiative,
+        MeleeAttackIntent, MoveIntent, MyTurn, Position, StatusEffects, StatusKind, Viewshed,
+        ACTION_COST, FOLLOW_DISTANCE, FOLLOW_LEASH, PLAYER_FACTION,
+    },
+    game_log::GameLog,
+    game_state::GameStatus,
+    level::Level,
+    raws::Raws,
+};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/// Acts for the player's companions, carrying out whatever they were told to do
+pub struct CompanionAISystem {}
+
+impl<'a> System<'a> for CompanionAISystem {
+    type SystemData = (
+        ReadExpect<'a, Level>,
+        ReadExpect<'a, Point>,
+        ReadExpect<'a, GameStatus>,
+        ReadExpect<'a, Raws>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        Entities<'a>,
+        ReadStorage<'a, Viewshed>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Companion>,
+        WriteStorage<'a, CompanionTarget>,
+        WriteStorage<'a, Initiative>,
+        WriteStorage<'a, MyTurn>,
+        WriteStorage<'a, MeleeAttackIntent>,
+        WriteStorage<'a, MoveIntent>,
+        ReadStorage<'a, StatusEffects>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, GameplayName>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            level,
+            player_pos,
+            game_status,
+            raws,
+            mut logger,
+            mut rng,
+            ents,
+            viewsheds,
+            positions,
+            mut companions,
+            mut companion_targets,
+            mut initiatives,
+            mut turns,
+            mut attack_intents,
+            mut move_intents,
+            statuses,
+            factions,
+            cstats,
+            names,
+        ) = data;
+
+        // Companions take their turns along with the monsters
+        if *game_status != GameStatus::MonsterTurn {
+            return;
+        }
+
+        // Everyone still standing who might be picked as a target
+        let actors: Vec<(Entity, Point, &str)> = (&ents, &positions, &factions, &cstats)
+            .join()
+            .filter(|(_, _, _, stats)| stats.hp > 0)
+            .map(|(ent, pos, faction, _)| (ent, Point::new(pos.x, pos.y), faction.name.as_str()))
+            .collect();
+
+        let mut acted: Vec<Entity> = Vec::new();
+        let mut attacks_over: Vec<Entity> = Vec::new();
+        for (ent, vs, pos, initiative, _, companion) in (
+            &ents,
+            &viewsheds,
+            &positions,
+            &mut initiatives,
+            &turns,
+            &mut companions,
+        )
+            .join()
+        {
+            initiative.spend(ACTION_COST);
+            acted.push(ent);
+
+            if statuses
+                .get(ent)
+                .is_some_and(|status| status.has(StatusKind::Confused))
+            {
+                if let Some(step_idx) = random_step(&level, pos, &mut rng) {
+                    move_to(&mut move_intents, &level, ent, step_idx);
+                }
+                continue;
+            }
+
+            let my_pos = Point::new(pos.x, pos.y);
+            let my_idx = level.xy_idx(pos.x, pos.y);
+
+            if companion.order == CompanionOrder::Attack {
+                let target = companion_targets.get(ent).and_then(|order| {
+                    actors
+                        .iter()
+                        .find(|(other, _, _)| *other == order.target)
+                        .map(|(other, other_pos, _)| (*other, *other_pos))
+                });
+                match target {
+                    Some((target_ent, target_pos)) => {
+                        if rltk::DistanceAlg::Pythagoras.distance2d(my_pos, target_pos) < 1.5 {
+                            attack(&mut attack_intents, ent, target_ent);
+                        } else {
+                            let step = path_step_next_to(&level, my_pos, target_pos)
+                                .or_else(|| step_by_distance(&level, my_pos, target_pos, true));
+                            if let Some(step_idx) = step {
+                                move_to(&mut move_intents, &level, ent, step_idx);
+                            }
+                        }
+                        continue;
+                    }
+                    None => {
+                        // Target is dead or gone, back to the player
+                        companion.order = CompanionOrder::Follow;
+                        attacks_over.push(ent);
+                        if let Some(name) = names.get(ent) {
+                            logger.log(format!("{} comes back to you.", name.name));
+                        }
+                    }
+                }
+            }
+
+            let faction = factions
+                .get(ent)
+                .map_or(PLAYER_FACTION, |faction| faction.name.as_str());
+            let hostile = nearest_hostile(&raws, faction, ent, my_pos, vs, &actors);
+
+            // Whatever stands next to it gets fought, no matter the order
+            if let Some((hostile_ent, hostile_pos)) = hostile {
+                if rltk::DistanceAlg::Pythagoras.distance2d(my_pos, hostile_pos) < 1.5 {
+                    attack(&mut attack_intents, ent, hostile_ent);
+                    continue;
+                }
+            }
+            if companion.order == CompanionOrder::Stay {
+                continue;
+            }
+
+            let player_dist = rltk::DistanceAlg::Pythagoras.distance2d(my_pos, *player_pos);
+            if let Some((_, hostile_pos)) = hostile {
+                if player_dist <= FOLLOW_LEASH {
+                    let step = path_step_next_to(&level, my_pos, hostile_pos)
+                        .or_else(|| step_by_distance(&level, my_pos, hostile_pos, true));
+                    if let Some(step_idx) = step {
+                        move_to(&mut move_intents, &level, ent, step_idx);
+                        continue;
+                    }
+                }
+            }
+            if player_dist > FOLLOW_DISTANCE {
+                if let Some(step_idx) = level.to_player.downhill_step(&level, my_idx) {
+                    move_to(&mut move_intents, &level, ent, step_idx);
+                }
+            }
+        }
+
+        for ent in acted {
+            turns.remove(ent);
+        }
+        for ent in attacks_over {
+            companion_targets.remove(ent);
+        }
+    }
+}
+
+fn attack(attack_intents: &mut WriteStorage<MeleeAttackIntent>, ent: Entity, target: Entity) {
+    attack_intents
+        .insert(ent, MeleeAttackIntent { target })
+        .expect("Should be able to insert melee attack intent to the companion");
+}
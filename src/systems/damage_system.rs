@@ -1,26 +1,39 @@
-use crate::components::{CombatStats, GameplayName, IncomingDamage};
+use crate::components::{CombatStats, GameplayName, IncomingDamage, KilledBy};
 use specs::prelude::*;
 
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (
+        Entities<'a>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, IncomingDamage>,
         ReadStorage<'a, GameplayName>,
+        WriteStorage<'a, KilledBy>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut all_stats, mut damages, gnames) = data;
+        let (ents, mut all_stats, mut damages, gnames, mut killed_by) = data;
 
-        for (mut stats, dmg, name) in (&mut all_stats, &damages, &gnames).join() {
+        for (ent, stats, dmg, name) in (&ents, &mut all_stats, &damages, &gnames).join() {
             let dmg_amount = dmg.amount.iter().sum::<i32>();
+            let was_alive = stats.hp > 0;
 
-            stats.hp -= dmg_amount;
+            // Negative damage heals, but never above max hp
+            stats.hp = i32::min(stats.hp - dmg_amount, stats.max_hp as i32);
             println!(
                 "{} ({}/{}) received {} dmg",
                 name.name, stats.hp, stats.max_hp, dmg_amount
             );
+
+            // Last one to hit gets the credit for the kill
+            if was_alive && stats.hp <= 0 {
+                if let Some(&killer) = dmg.sources.last() {
+                    killed_by
+                        .insert(ent, KilledBy { killer })
+                        .expect("Should be able to insert the killer of a dead entity");
+                }
+            }
         }
 
         // Remove InflictedDamage from all entities
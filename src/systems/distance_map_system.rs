@@ -0,0 +1,39 @@
+use crate::{
+    components::{Item, Position},
+    game_state::GameStatus,
+    level::Level,
+};
+use rltk::Point;
+use specs::prelude::*;
+
+/// Keeps the level's distance maps up to date, so that every monster can roll downhill on them
+/// instead of running its own path search. They only get rebuilt once the player or the items
+/// on the floor have moved, not on every monster turn.
+pub struct DistanceMapSystem {}
+
+impl<'a> System<'a> for DistanceMapSystem {
+    type SystemData = (
+        WriteExpect<'a, Level>,
+        ReadExpect<'a, Point>,
+        ReadExpect<'a, GameStatus>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Item>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut level, player_pos, game_status, positions, items) = data;
+
+        // Nobody rolls on them outside of the monster turn
+        if *game_status != GameStatus::MonsterTurn {
+            return;
+        }
+
+        let player_idx = level.xy_idx(player_pos.x, player_pos.y);
+        // Carried items have no position, so only those lying around count
+        let item_idx: Vec<usize> = (&positions, &items)
+            .join()
+            .map(|(pos, _)| level.xy_idx(pos.x, pos.y))
+            .collect();
+        level.refresh_distance_maps(player_idx, item_idx);
+    }
+}
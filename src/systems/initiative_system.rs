@@ -0,0 +1,81 @@
+use crate::components::{Initiative, MyTurn, StatusEffects, StatusKind, ACTION_COST};
+use crate::game_state::GameStatus;
+use specs::prelude::*;
+
+/// Lets time pass until at least one actor has enough energy to act, then hands them the turn
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Initiative>,
+        WriteStorage<'a, MyTurn>,
+        ReadStorage<'a, StatusEffects>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (ents, mut initiatives, mut turns, statuses) = data;
+
+        // Someone still has to use their turn
+        if !turns.is_empty() {
+            return;
+        }
+        // Nobody would ever get a turn
+        if !initiatives.join().any(|initiative| initiative.speed > 0) {
+            return;
+        }
+
+        while turns.is_empty() {
+            for (ent, initiative) in (&ents, &mut initiatives).join() {
+                let is_hasted = statuses
+                    .get(ent)
+                    .is_some_and(|status| status.has(StatusKind::Hasted));
+                initiative.energy += if is_hasted {
+                    initiative.speed * 2
+                } else {
+                    initiative.speed
+                };
+                if initiative.energy >= ACTION_COST {
+                    turns
+                        .insert(ent, MyTurn {})
+                        .expect("Should be able to give a turn to an actor with enough energy");
+                }
+            }
+        }
+    }
+}
+
+/// Runs after the AIs. Whoever got a turn in the monster turn but wasn't handled by any AI
+/// (e.g. an actor with an Initiative and nothing to act with) just waits, so that the scheduler
+/// doesn't stall on a turn nobody is going to use.
+pub struct UnhandledTurnSystem {}
+
+impl<'a> System<'a> for UnhandledTurnSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, GameStatus>,
+        WriteStorage<'a, Initiative>,
+        WriteStorage<'a, MyTurn>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (ents, player_ent, game_status, mut initiatives, mut turns) = data;
+
+        if *game_status != GameStatus::MonsterTurn {
+            return;
+        }
+
+        let idle: Vec<Entity> = (&ents, &turns)
+            .join()
+            .map(|(ent, _)| ent)
+            .filter(|&ent| ent != *player_ent)
+            .collect();
+        for ent in idle {
+            if let Some(initiative) = initiatives.get_mut(ent) {
+                initiative.spend(ACTION_COST);
+            }
+            turns.remove(ent);
+        }
+    }
+}
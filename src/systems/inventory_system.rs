@@ -0,0 +1,370 @@
+use crate::{
+    components::{
+        AppliesStatus, AreaOfEffect, CombatStats, Confusion, Consumable, Equippable, Equipped,
+        GameplayName, Healer, InBackpack, IncomingDamage, InflictsDamage, Position, Ranged,
+        StatusEffect, StatusEffects, StatusKind, Viewshed, WantsToDrop, WantsToEquip,
+        WantsToPickUp, WantsToUnequip, WantsToUse,
+    },
+    game_log::GameLog,
+    level::Level,
+};
+use rltk::Point;
+use specs::prelude::*;
+
+/// Moves items that someone wants to pick up from the floor into their backpack
+pub struct ItemCollectionSystem {}
+
+impl<'a> System<'a> for ItemCollectionSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, WantsToPickUp>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, GameplayName>,
+        WriteStorage<'a, InBackpack>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_ent, mut logger, mut pickups, mut positions, gnames, mut backpack) = data;
+
+        for pickup in pickups.join() {
+            positions.remove(pickup.item);
+            backpack
+                .insert(
+                    pickup.item,
+                    InBackpack {
+                        owner: pickup.collected_by,
+                    },
+                )
+                .expect("Should be able to insert picked up item into backpack storage");
+
+            if pickup.collected_by == *player_ent {
+                if let Some(item_name) = gnames.get(pickup.item) {
+                    logger.log(format!("You pick up the {}.", item_name.name));
+                }
+            }
+        }
+
+        // Clean up pickup intents from ALL entities.
+        pickups.clear();
+    }
+}
+
+/// Applies the effects of used items and destroys the consumable ones.
+/// Ranged items affect whoever stands in the area around their target, the rest affect their user.
+/// Only carried items get used, and ranged ones only on a visible target within their range.
+pub struct ItemUseSystem {}
+
+impl<'a> System<'a> for ItemUseSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Level>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, WantsToUse>,
+        ReadStorage<'a, GameplayName>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Healer>,
+        ReadStorage<'a, InflictsDamage>,
+        ReadStorage<'a, Confusion>,
+        ReadStorage<'a, Ranged>,
+        ReadStorage<'a, AreaOfEffect>,
+        ReadStorage<'a, Consumable>,
+        WriteStorage<'a, IncomingDamage>,
+        ReadStorage<'a, AppliesStatus>,
+        WriteStorage<'a, StatusEffects>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Viewshed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            ents,
+            player_ent,
+            level,
+            mut logger,
+            mut use_intents,
+            gnames,
+            cstats,
+            healers,
+            damagers,
+            confusers,
+            ranged,
+            aoes,
+            consumables,
+            mut inc_dmg,
+            status_appliers,
+            mut statuses,
+            backpack,
+            positions,
+            viewsheds,
+        ) = data;
+
+        for (user_ent, use_intent, user_name) in (&ents, &use_intents, &gnames).join() {
+            let item_name = gnames
+                .get(use_intent.item)
+                .map_or("item", |gname| gname.name.as_str());
+
+            let is_carried = backpack
+                .get(use_intent.item)
+                .is_some_and(|carried| carried.owner == user_ent);
+            if !is_carried {
+                logger.log(format!(
+                    "{} doesn't carry the {}.",
+                    user_name.name, item_name
+                ));
+                continue;
+            }
+
+            let targets: Vec<Entity> = match (ranged.get(use_intent.item), use_intent.target) {
+                (None, _) => vec![user_ent],
+                (Some(_), None) => {
+                    // Don't waste the item, it was never aimed
+                    logger.log(format!("The {} needs a target.", item_name));
+                    continue;
+                }
+                (Some(item_range), Some(target)) => {
+                    let in_reach = match (positions.get(user_ent), viewsheds.get(user_ent)) {
+                        (Some(pos), Some(vs)) => {
+                            let distance = rltk::DistanceAlg::Pythagoras
+                                .distance2d(Point::new(pos.x, pos.y), target);
+                            distance <= item_range.range as f32
+                                && vs.visible_tiles.contains(&target)
+                        }
+                        _ => false,
+                    };
+                    if !in_reach {
+                        logger.log(format!("The {} can't reach that far.", item_name));
+                        continue;
+                    }
+
+                    let radius = aoes.get(use_intent.item).map_or(0, |aoe| aoe.radius);
+                    level
+                        .area_of_effect(target, radius)
+                        .iter()
+                        .flat_map(|p| level.tile_content[level.xy_idx(p.x, p.y)].iter())
+                        .filter(|&&ent| cstats.get(ent).is_some())
+                        .copied()
+                        .collect()
+                }
+            };
+
+            if user_ent != *player_ent {
+                logger.log(format!("{} uses the {}.", user_name.name, item_name));
+            }
+            if targets.is_empty() {
+                logger.log(format!("The {} hits nothing.", item_name));
+            }
+
+            for target in targets.iter() {
+                let target_name = gnames
+                    .get(*target)
+                    .map_or("something", |gname| gname.name.as_str());
+
+                // Healing goes through the damage pipeline as negative damage
+                if let Some(healer) = healers.get(use_intent.item) {
+                    IncomingDamage::new(&mut inc_dmg, *target, -(healer.heal_amount as i32), None);
+                    if *target == *player_ent {
+                        logger.log(format!(
+                            "You use the {}, healing {} hp.",
+                            item_name, healer.heal_amount
+                        ));
+                    }
+                }
+
+                if let Some(damager) = damagers.get(use_intent.item) {
+                    IncomingDamage::new(&mut inc_dmg, *target, damager.damage, Some(user_ent));
+                    logger.log(format!(
+                        "The {} hits {} for {} hp.",
+                        item_name, target_name, damager.damage
+                    ));
+                }
+
+                let mut effects: Vec<StatusEffect> = Vec::new();
+                if let Some(confuser) = confusers.get(use_intent.item) {
+                    effects.push(StatusEffect {
+                        kind: StatusKind::Confused,
+                        turns: confuser.turns,
+                        source: Some(user_ent),
+                    });
+                }
+                if let Some(applier) = status_appliers.get(use_intent.item) {
+                    effects.push(StatusEffect {
+                        source: Some(user_ent),
+                        ..applier.effect
+                    });
+                }
+                for effect in effects {
+                    StatusEffects::apply(&mut statuses, *target, effect);
+                    logger.log(format!("{} is {}.", target_name, effect.kind.describe()));
+                }
+            }
+
+            if consumables.get(use_intent.item).is_some() {
+                ents.delete(use_intent.item)
+                    .expect("Should be able to delete a consumed item");
+            }
+        }
+
+        // Clean up use intents from ALL entities.
+        use_intents.clear();
+    }
+}
+
+/// Puts on and takes off worn items. Whatever already sits in the slot gets taken off first.
+/// Only carried items can be put on, and only their wearer can take them off.
+pub struct ItemEquipSystem {}
+
+impl<'a> System<'a> for ItemEquipSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, WantsToEquip>,
+        WriteStorage<'a, WantsToUnequip>,
+        ReadStorage<'a, GameplayName>,
+        ReadStorage<'a, Equippable>,
+        WriteStorage<'a, Equipped>,
+        ReadStorage<'a, InBackpack>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            ents,
+            player_ent,
+            mut logger,
+            mut equip_intents,
+            mut unequip_intents,
+            gnames,
+            equippables,
+            mut equipped,
+            backpack,
+        ) = data;
+
+        let name_of = |ent: Entity| gnames.get(ent).map_or("item", |gname| gname.name.as_str());
+
+        for (owner_ent, unequip_intent) in (&ents, &unequip_intents).join() {
+            let is_worn = equipped
+                .get(unequip_intent.item)
+                .is_some_and(|worn| worn.owner == owner_ent);
+            if !is_worn {
+                continue;
+            }
+            equipped.remove(unequip_intent.item);
+            if owner_ent == *player_ent {
+                logger.log(format!("You unequip the {}.", name_of(unequip_intent.item)));
+            }
+        }
+
+        for (owner_ent, equip_intent) in (&ents, &equip_intents).join() {
+            let slot = match equippables.get(equip_intent.item) {
+                Some(equippable) => equippable.slot,
+                None => continue,
+            };
+            let is_carried = backpack
+                .get(equip_intent.item)
+                .is_some_and(|carried| carried.owner == owner_ent);
+            if !is_carried {
+                continue;
+            }
+
+            // Swap out whatever the owner wears in that slot
+            let worn: Vec<Entity> = (&ents, &equipped)
+                .join()
+                .filter(|(_, worn)| worn.owner == owner_ent && worn.slot == slot)
+                .map(|(ent, _)| ent)
+                .collect();
+            for worn_ent in worn {
+                equipped.remove(worn_ent);
+                if owner_ent == *player_ent {
+                    logger.log(format!("You unequip the {}.", name_of(worn_ent)));
+                }
+            }
+
+            equipped
+                .insert(
+                    equip_intent.item,
+                    Equipped {
+                        owner: owner_ent,
+                        slot,
+                    },
+                )
+                .expect("Should be able to insert equipped item into equipped storage");
+            if owner_ent == *player_ent {
+                logger.log(format!("You equip the {}.", name_of(equip_intent.item)));
+            }
+        }
+
+        // Clean up equip intents from ALL entities.
+        equip_intents.clear();
+        unequip_intents.clear();
+    }
+}
+
+/// Puts dropped items back on the floor under whoever dropped them
+pub struct ItemDropSystem {}
+
+impl<'a> System<'a> for ItemDropSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, WantsToDrop>,
+        ReadStorage<'a, GameplayName>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Equipped>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            ents,
+            player_ent,
+            mut logger,
+            mut drop_intents,
+            gnames,
+            mut positions,
+            mut backpack,
+            mut equipped,
+        ) = data;
+
+        for (dropper_ent, drop_intent) in (&ents, &drop_intents).join() {
+            let drop_pos = match positions.get(dropper_ent) {
+                Some(pos) => Position { x: pos.x, y: pos.y },
+                None => continue,
+            };
+            let is_carried = backpack
+                .get(drop_intent.item)
+                .is_some_and(|carried| carried.owner == dropper_ent);
+            if !is_carried {
+                logger.log(format!(
+                    "{} doesn't carry the {}.",
+                    gnames
+                        .get(dropper_ent)
+                        .map_or("Someone", |gname| gname.name.as_str()),
+                    gnames
+                        .get(drop_intent.item)
+                        .map_or("item", |gname| gname.name.as_str())
+                ));
+                continue;
+            }
+
+            positions
+                .insert(drop_intent.item, drop_pos)
+                .expect("Should be able to insert position of the dropped item");
+            backpack.remove(drop_intent.item);
+            // Nobody wears what lies on the floor
+            equipped.remove(drop_intent.item);
+
+            if dropper_ent == *player_ent {
+                if let Some(item_name) = gnames.get(drop_intent.item) {
+                    logger.log(format!("You drop the {}.", item_name.name));
+                }
+            }
+        }
+
+        // Clean up drop intents from ALL entities.
+        drop_intents.clear();
+    }
+}
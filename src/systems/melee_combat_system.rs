@@ -1,4 +1,4 @@
-use crate::{components::{CombatStats, GameplayName, IncomingDamage, MeleeAttackIntent}, game_log::GameLog};
+use crate::{components::{defense_bonus_of, power_bonus_of, CombatStats, DefenseBonus, Equipped, GameplayName, IncomingDamage, MeleeAttackIntent, MeleePowerBonus}, game_log::GameLog};
 use specs::prelude::*;
 
 pub struct MeleeCombatSystem {}
@@ -11,13 +11,26 @@ impl<'a> System<'a> for MeleeCombatSystem {
         ReadStorage<'a, GameplayName>,
         ReadStorage<'a, CombatStats>,
         WriteStorage<'a, IncomingDamage>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, MeleePowerBonus>,
+        ReadStorage<'a, DefenseBonus>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (ents, mut logger, mut melee_attkrs, gnames, cstats, mut inc_dmg) = data;
+        let (
+            ents,
+            mut logger,
+            mut melee_attkrs,
+            gnames,
+            cstats,
+            mut inc_dmg,
+            equipped,
+            power_bonuses,
+            defense_bonuses,
+        ) = data;
 
         // Go thru each from the standpoint of the ATTACKER
-        for (_, melee_attack, attacker_name, attacker_stats) in
+        for (attacker_ent, melee_attack, attacker_name, attacker_stats) in
             (&ents, &melee_attkrs, &gnames, &cstats).join()
         {
             // Ignore if attacker already ded
@@ -39,12 +52,15 @@ impl<'a> System<'a> for MeleeCombatSystem {
                 .get(victim_ent)
                 .expect("Victim GameplayName is obtainable in MeleeCombatSystem");
 
-            let dmg = i32::max(0, attacker_stats.power as i32 - victim_stats.defense);
+            // Whatever both of them wear counts too
+            let power = attacker_stats.power as i32 + power_bonus_of(attacker_ent, &equipped, &power_bonuses);
+            let defense = victim_stats.defense + defense_bonus_of(victim_ent, &equipped, &defense_bonuses);
+            let dmg = i32::max(0, power - defense);
 
             if dmg == 0 {
                 logger.log(format!("{} is unable to hurt {}", &attacker_name.name, &victim_name.name));
             } else {
-                IncomingDamage::new(&mut inc_dmg, victim_ent, dmg);
+                IncomingDamage::new(&mut inc_dmg, victim_ent, dmg, Some(attacker_ent));
                 logger.log(format!(
                     "{} hits {} for {} dmg",
                     &attacker_name.name, &victim_name.name, dmg
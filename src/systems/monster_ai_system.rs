@@ -1,39 +1,65 @@
 use crate::{
-    components::{GameplayName, MeleeAttackIntent, MonsterChar, Position, Viewshed},
+    components::{
+        AiState, CombatStats, Faction, IdleBehaviour, Initiative, MeleeAttackIntent, MonsterAI,
+        MonsterChar, MoveIntent, MyTurn, Position, RangedAttackIntent, RangedAttacker, Reaction,
+        StatusEffects, StatusKind, Viewshed, ACTION_COST, FLEE_HP_FRACTION,
+    },
     game_state::GameStatus,
     level::Level,
+    raws::Raws,
 };
-use rltk::{Point};
+use rltk::{BaseMap, Point, RandomNumberGenerator};
 use specs::prelude::*;
 
+/// Archers back off when their target gets closer than this
+const ARCHER_MIN_DISTANCE: f32 = 3.0;
+
 pub struct MonsterAISystem {}
 
 impl<'a> System<'a> for MonsterAISystem {
     type SystemData = (
-        WriteExpect<'a, Level>,
-        ReadExpect<'a, Point>,
+        ReadExpect<'a, Level>,
         ReadExpect<'a, Entity>,
         ReadExpect<'a, GameStatus>,
         Entities<'a>,
-        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, Viewshed>,
         ReadStorage<'a, MonsterChar>,
-        ReadStorage<'a, GameplayName>,
-        WriteStorage<'a, Position>,
+        ReadStorage<'a, Position>,
         WriteStorage<'a, MeleeAttackIntent>,
+        WriteStorage<'a, Initiative>,
+        WriteStorage<'a, MyTurn>,
+        ReadStorage<'a, RangedAttacker>,
+        WriteStorage<'a, RangedAttackIntent>,
+        ReadStorage<'a, StatusEffects>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteStorage<'a, MonsterAI>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, MoveIntent>,
+        ReadStorage<'a, Faction>,
+        ReadExpect<'a, Raws>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            mut level,
-            player_pos,
+            level,
             player_ent,
             game_status,
             ents,
-            mut viewsheds,
+            viewsheds,
             monsters,
-            gnames,
-            mut positions,
+            positions,
             mut attack_intents,
+            mut initiatives,
+            mut turns,
+            ranged_attackers,
+            mut ranged_intents,
+            statuses,
+            mut rng,
+            mut ais,
+            cstats,
+            mut move_intents,
+            factions,
+            raws,
         ) = data;
 
         // Only run if it is MonsterTurn
@@ -41,45 +67,286 @@ impl<'a> System<'a> for MonsterAISystem {
             return;
         }
 
-        for (ent, mut vs, _, _gname, mut pos) in
-            (&ents, &mut viewsheds, &monsters, &gnames, &mut positions).join()
+        // Everyone still standing who might be picked as a target
+        let actors: Vec<(Entity, Point, &str)> = (&ents, &positions, &factions, &cstats)
+            .join()
+            .filter(|(_, _, _, stats)| stats.hp > 0)
+            .map(|(ent, pos, faction, _)| (ent, Point::new(pos.x, pos.y), faction.name.as_str()))
+            .collect();
+
+        let mut acted: Vec<Entity> = Vec::new();
+        for (ent, vs, _, pos, initiative, _, ai, stats) in (
+            &ents,
+            &viewsheds,
+            &monsters,
+            &positions,
+            &mut initiatives,
+            &turns,
+            &mut ais,
+            &cstats,
+        )
+            .join()
         {
-            let dist =
-                rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+            // Whatever the monster ends up doing, even nothing, takes a regular action
+            initiative.spend(ACTION_COST);
+            acted.push(ent);
 
-            if dist < 1.5 {
-                attack_intents
-                    .insert(
-                        ent,
-                        MeleeAttackIntent {
-                            target: *player_ent,
-                        },
-                    )
-                    .expect("Attack intent with player as a target entity.");
+            // Confused monsters stumble into a random direction instead
+            if statuses
+                .get(ent)
+                .is_some_and(|status| status.has(StatusKind::Confused))
+            {
+                if let Some(step_idx) = random_step(&level, pos, &mut rng) {
+                    move_to(&mut move_intents, &level, ent, step_idx);
+                }
                 continue;
             }
 
-            if !vs.visible_tiles.contains(&*player_pos) {
+            let monster_pos = Point::new(pos.x, pos.y);
+            let monster_idx = level.xy_idx(pos.x, pos.y);
+            let target = factions.get(ent).and_then(|faction| {
+                nearest_hostile(&raws, &faction.name, ent, monster_pos, vs, &actors)
+            });
+            let is_hurt = (stats.hp as f32) < stats.max_hp as f32 * FLEE_HP_FRACTION;
+
+            ai.state = match (target.is_some(), ai.state) {
+                (true, _) if is_hurt => AiState::Fleeing,
+                (true, _) => AiState::Hunting,
+                (false, AiState::Hunting) => AiState::Searching,
+                (false, AiState::Fleeing) => AiState::Idle,
+                (false, state) => state,
+            };
+            if let Some((_, target_pos)) = target {
+                ai.last_seen = Some(target_pos);
+            }
+
+            match ai.state {
+                AiState::Idle => {
+                    // Wanderers drift towards the loot lying around, and mill about once there
+                    if ai.idle == IdleBehaviour::Wander && rng.range(0, 2) == 0 {
+                        let step = level
+                            .to_items
+                            .downhill_step(&level, monster_idx)
+                            .or_else(|| random_step(&level, pos, &mut rng));
+                        if let Some(step_idx) = step {
+                            move_to(&mut move_intents, &level, ent, step_idx);
+                        }
+                    }
+                    continue;
+                }
+                AiState::Searching => {
+                    let last_seen = match ai.last_seen {
+                        Some(last_seen) if last_seen != monster_pos => last_seen,
+                        // Nobody here, give up
+                        _ => {
+                            ai.state = AiState::Idle;
+                            ai.last_seen = None;
+                            continue;
+                        }
+                    };
+                    match path_step(&level, monster_pos, last_seen) {
+                        Some(step_idx) => move_to(&mut move_intents, &level, ent, step_idx),
+                        None => {
+                            ai.state = AiState::Idle;
+                            ai.last_seen = None;
+                        }
+                    }
+                    continue;
+                }
+                AiState::Fleeing | AiState::Hunting => {}
+            }
+
+            // Only Hunting and Fleeing are left, and both need a target in sight
+            let (target_ent, target_pos) = match target {
+                Some(target) => target,
+                None => continue,
+            };
+            let target_is_player = target_ent == *player_ent;
+            let dist = rltk::DistanceAlg::Pythagoras.distance2d(monster_pos, target_pos);
+
+            if ai.state == AiState::Fleeing {
+                if let Some(step_idx) = flee_step(&level, monster_pos, target_pos, target_is_player)
+                {
+                    move_to(&mut move_intents, &level, ent, step_idx);
+                    continue;
+                }
+                // Cornered, fight back
+            }
+
+            // Archers keep their distance and shoot instead of closing in
+            if let Some(weapon) = ranged_attackers.get(ent) {
+                if dist < ARCHER_MIN_DISTANCE {
+                    if let Some(step_idx) =
+                        flee_step(&level, monster_pos, target_pos, target_is_player)
+                    {
+                        move_to(&mut move_intents, &level, ent, step_idx);
+                        continue;
+                    }
+                } else if dist <= weapon.range as f32
+                    && level.has_clear_shot(monster_pos, target_pos)
+                {
+                    ranged_intents
+                        .insert(ent, RangedAttackIntent { target: target_pos })
+                        .expect("Should be able to insert ranged attack intent at the target");
+                    continue;
+                }
+            }
+
+            if dist < 1.5 {
+                attack_intents
+                    .insert(ent, MeleeAttackIntent { target: target_ent })
+                    .expect("Should be able to insert melee attack intent at the target");
                 continue;
             }
 
-            // Pathfind and move the monster
-            let path = rltk::a_star_search(
-                level.xy_idx(pos.x, pos.y) as i32,
-                level.xy_idx(player_pos.x, player_pos.y) as i32,
-                &mut *level,
-            );
-
-            if path.success && path.steps.len() > 1 {
-                // Doesn't help. Monsters still can step on each other.
-                // if level.is_tile_blocked(path.steps[1]) {
-                //     continue;
-                // }
-                pos.x = path.steps[1] as i32 % level.width;
-                pos.y = path.steps[1] as i32 / level.width;
-                level.block_tile(path.steps[1]);
-                vs.is_dirty = true;
+            if let Some(step_idx) = approach_step(&level, monster_pos, target_pos, target_is_player)
+            {
+                move_to(&mut move_intents, &level, ent, step_idx);
             }
         }
+
+        for ent in acted {
+            turns.remove(ent);
+        }
+    }
+}
+
+/// Asks MovementSystem to move the monster onto the tile
+pub(crate) fn move_to(
+    move_intents: &mut WriteStorage<MoveIntent>,
+    level: &Level,
+    ent: Entity,
+    idx: usize,
+) {
+    let (x, y) = level.idx_xy(idx);
+    move_intents
+        .insert(
+            ent,
+            MoveIntent {
+                destination: Point::new(x, y),
+            },
+        )
+        .expect("Should be able to insert move intent to the monster");
+}
+
+/// First step of the A* path from `from` to `to`. Rets None if there's no way there.
+/// Only used for one-off goals, the player is approached through the level's distance maps.
+fn path_step(level: &Level, from: Point, to: Point) -> Option<usize> {
+    let path = rltk::a_star_search(
+        level.xy_idx(from.x, from.y) as i32,
+        level.xy_idx(to.x, to.y) as i32,
+        level,
+    );
+    if path.success && path.steps.len() > 1 {
+        Some(path.steps[1])
+    } else {
+        None
+    }
+}
+
+/// Nearest actor in sight that the `faction` is hostile to, along with where it stands
+pub(crate) fn nearest_hostile(
+    raws: &Raws,
+    faction: &str,
+    me: Entity,
+    from: Point,
+    vs: &Viewshed,
+    actors: &[(Entity, Point, &str)],
+) -> Option<(Entity, Point)> {
+    actors
+        .iter()
+        .filter(|(ent, pos, other_faction)| {
+            *ent != me
+                && vs.visible_tiles.contains(pos)
+                && raws.reaction(faction, other_faction) == Reaction::Hostile
+        })
+        .map(|(ent, pos, _)| (*ent, *pos))
+        .min_by(|(_, a), (_, b)| {
+            let dist_a = rltk::DistanceAlg::Pythagoras.distance2d(from, *a);
+            let dist_b = rltk::DistanceAlg::Pythagoras.distance2d(from, *b);
+            dist_a.total_cmp(&dist_b)
+        })
+}
+
+/// Step towards the target. The whole pack shares the level's map to the player,
+/// so nobody runs a path search of its own for them. Anyone else is reached by A*.
+fn approach_step(level: &Level, from: Point, target: Point, is_player: bool) -> Option<usize> {
+    if is_player {
+        let from_idx = level.xy_idx(from.x, from.y);
+        level.to_player.downhill_step(level, from_idx)
+    } else {
+        path_step_next_to(level, from, target)
+            .or_else(|| step_by_distance(level, from, target, true))
+    }
+}
+
+/// First step of the A* path to the free tile next to `target` that is the closest to `from`.
+/// The target's own tile is blocked by the target, so A* could never end on it.
+pub(crate) fn path_step_next_to(level: &Level, from: Point, target: Point) -> Option<usize> {
+    let beside = level
+        .get_available_exits(level.xy_idx(target.x, target.y))
+        .into_iter()
+        .map(|(idx, _)| {
+            let (x, y) = level.idx_xy(idx);
+            Point::new(x, y)
+        })
+        .min_by(|a, b| {
+            let dist_a = rltk::DistanceAlg::Pythagoras.distance2d(from, *a);
+            let dist_b = rltk::DistanceAlg::Pythagoras.distance2d(from, *b);
+            dist_a.total_cmp(&dist_b)
+        })?;
+    path_step(level, from, beside)
+}
+
+/// Step away from the threat, along the level's flee map if it is the player
+fn flee_step(level: &Level, from: Point, threat: Point, is_player: bool) -> Option<usize> {
+    if is_player {
+        let from_idx = level.xy_idx(from.x, from.y);
+        level.from_player.downhill_step(level, from_idx)
+    } else {
+        step_by_distance(level, from, threat, false)
+    }
+}
+
+/// Neighbouring free tile that gets the monster the closest to `other`, or the farthest from it
+/// unless `closer` is set. Rets None if no step beats the current distance.
+pub(crate) fn step_by_distance(
+    level: &Level,
+    from: Point,
+    other: Point,
+    closer: bool,
+) -> Option<usize> {
+    let current_dist = rltk::DistanceAlg::Pythagoras.distance2d(from, other);
+    let steps = level
+        .get_available_exits(level.xy_idx(from.x, from.y))
+        .into_iter()
+        .map(|(idx, _)| {
+            let (x, y) = level.idx_xy(idx);
+            let dist = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(x, y), other);
+            (idx, dist)
+        });
+    let best = if closer {
+        steps
+            .filter(|(_, dist)| *dist < current_dist)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    } else {
+        steps
+            .filter(|(_, dist)| *dist > current_dist)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    };
+    best.map(|(idx, _)| idx)
+}
+
+/// Any free neighbouring tile
+pub(crate) fn random_step(
+    level: &Level,
+    pos: &Position,
+    rng: &mut RandomNumberGenerator,
+) -> Option<usize> {
+    let exits = level.get_available_exits(level.xy_idx(pos.x, pos.y));
+    if exits.is_empty() {
+        None
+    } else {
+        Some(exits[rng.range(0, exits.len())].0)
     }
 }
@@ -0,0 +1,129 @@
+use crate::{
+    components::{MoveIntent, Position, TileBlocker, Viewshed},
+    level::Level,
+};
+use rltk::BaseMap;
+use specs::prelude::*;
+use std::collections::HashMap;
+
+/// Carries out the move intents, making sure no two tile blockers ever end up on one tile.
+///
+/// - A step onto a free tile goes through. When several want the same one, the first in
+///   entity order gets it and the rest wait.
+/// - A step onto a tile whose blocker moves away waits for that move to be decided first.
+/// - Two blockers stepping onto each other's tile swap places.
+/// - A step onto a tile whose blocker stays, or onto a wall, waits.
+///
+/// Waiting still costs the turn, the AI already spent the initiative.
+pub struct MovementSystem {}
+
+#[derive(Clone, Copy)]
+struct Step {
+    ent: Entity,
+    from: usize,
+    to: usize,
+}
+
+enum Resolution {
+    Move(Step),
+    Swap(Step, Step),
+    Wait(Entity),
+}
+
+impl<'a> System<'a> for MovementSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Level>,
+        WriteStorage<'a, MoveIntent>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, TileBlocker>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (ents, level, mut move_intents, mut positions, mut viewsheds, blockers) = data;
+
+        // Whoever blocks a tile, by the tile index
+        let mut occupants: HashMap<usize, Entity> = (&ents, &positions, &blockers)
+            .join()
+            .map(|(ent, pos, _)| (level.xy_idx(pos.x, pos.y), ent))
+            .collect();
+
+        let mut pending: Vec<Step> = Vec::new();
+        let mut moves: Vec<(Entity, usize)> = Vec::new();
+        for (ent, intent, pos) in (&ents, &move_intents, &positions).join() {
+            let dest = intent.destination;
+            if dest.x < 0 || dest.x >= level.width || dest.y < 0 || dest.y >= level.height {
+                continue;
+            }
+            let step = Step {
+                ent,
+                from: level.xy_idx(pos.x, pos.y),
+                to: level.xy_idx(dest.x, dest.y),
+            };
+            // Anything that doesn't block can go wherever it likes
+            if blockers.contains(ent) {
+                pending.push(step);
+            } else {
+                moves.push((ent, step.to));
+            }
+        }
+
+        while let Some(resolution) = next_resolution(&level, &pending, &occupants) {
+            let decided = match resolution {
+                Resolution::Move(step) => {
+                    occupants.remove(&step.from);
+                    occupants.insert(step.to, step.ent);
+                    moves.push((step.ent, step.to));
+                    vec![step.ent]
+                }
+                Resolution::Swap(a, b) => {
+                    occupants.insert(a.to, a.ent);
+                    occupants.insert(b.to, b.ent);
+                    moves.push((a.ent, a.to));
+                    moves.push((b.ent, b.to));
+                    vec![a.ent, b.ent]
+                }
+                Resolution::Wait(ent) => vec![ent],
+            };
+            pending.retain(|step| !decided.contains(&step.ent));
+        }
+        // Whatever is left is stuck in a ring of three or more, waiting on each other. They wait.
+
+        for (ent, idx) in moves {
+            if let Some(pos) = positions.get_mut(ent) {
+                (pos.x, pos.y) = level.idx_xy(idx);
+            }
+            if let Some(vs) = viewsheds.get_mut(ent) {
+                vs.is_dirty = true;
+            }
+        }
+
+        move_intents.clear();
+    }
+}
+
+/// First step that can be decided given who stands where. Rets None once nothing can be decided.
+fn next_resolution(
+    level: &Level,
+    pending: &[Step],
+    occupants: &HashMap<usize, Entity>,
+) -> Option<Resolution> {
+    for step in pending {
+        if step.to == step.from || level.is_opaque(step.to) {
+            return Some(Resolution::Wait(step.ent));
+        }
+        match occupants.get(&step.to) {
+            None => return Some(Resolution::Move(*step)),
+            Some(&occupant) => match pending.iter().find(|other| other.ent == occupant) {
+                Some(other) if other.to == step.from => {
+                    return Some(Resolution::Swap(*step, *other))
+                }
+                // The occupant may still clear the way
+                Some(_) => continue,
+                None => return Some(Resolution::Wait(step.ent)),
+            },
+        }
+    }
+    None
+}
@@ -0,0 +1,142 @@
+use crate::{
+    components::{
+        defense_bonus_of, ranged_attack_of, CombatStats, DefenseBonus, Equipped, GameplayName,
+        IncomingDamage, Position, RangedAttackIntent, RangedAttacker, RangedWeapon, TileBlocker,
+        Viewshed,
+    },
+    game_log::GameLog,
+    level::Level,
+};
+use rltk::Point;
+use specs::prelude::*;
+
+/// Resolves shots along the line of fire. The first creature (or other tile blocker) in the way
+/// takes the hit, walls stop the shot.
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Level>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, RangedAttackIntent>,
+        ReadStorage<'a, RangedAttacker>,
+        ReadStorage<'a, Viewshed>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, GameplayName>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, TileBlocker>,
+        WriteStorage<'a, IncomingDamage>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, DefenseBonus>,
+        ReadStorage<'a, RangedWeapon>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            ents,
+            level,
+            mut logger,
+            mut ranged_intents,
+            ranged_attackers,
+            viewsheds,
+            positions,
+            gnames,
+            cstats,
+            blockers,
+            mut inc_dmg,
+            equipped,
+            defense_bonuses,
+            ranged_weapons,
+        ) = data;
+
+        for (attacker_ent, intent, vs, pos, attacker_name, attacker_stats) in (
+            &ents,
+            &ranged_intents,
+            &viewsheds,
+            &positions,
+            &gnames,
+            &cstats,
+        )
+            .join()
+        {
+            // Ignore if attacker already ded
+            if attacker_stats.hp <= 0 {
+                continue;
+            }
+
+            let weapon =
+                match ranged_attack_of(attacker_ent, &ranged_attackers, &equipped, &ranged_weapons)
+                {
+                    Some(weapon) => weapon,
+                    None => {
+                        logger.log(format!("{} has nothing to shoot with.", attacker_name.name));
+                        continue;
+                    }
+                };
+
+            let origin = Point::new(pos.x, pos.y);
+            let distance = rltk::DistanceAlg::Pythagoras.distance2d(origin, intent.target);
+            if distance > weapon.range as f32 || !vs.visible_tiles.contains(&intent.target) {
+                logger.log(format!("{} can't shoot that far.", attacker_name.name));
+                continue;
+            }
+
+            let victim = level
+                .line_of_fire(origin, intent.target)
+                .into_iter()
+                .find_map(|p| {
+                    level.tile_content[level.xy_idx(p.x, p.y)]
+                        .iter()
+                        .find(|&&ent| {
+                            ent != attacker_ent
+                                && (blockers.get(ent).is_some() || cstats.get(ent).is_some())
+                        })
+                        .copied()
+                });
+
+            let victim_ent = match victim {
+                Some(victim_ent) => victim_ent,
+                None => {
+                    logger.log(format!("{} shoots and misses.", attacker_name.name));
+                    continue;
+                }
+            };
+            let victim_name = gnames
+                .get(victim_ent)
+                .map_or("something", |gname| gname.name.as_str());
+
+            let victim_stats = match cstats.get(victim_ent) {
+                Some(stats) if stats.hp > 0 => stats,
+                // Shot got stuck in something that can't be hurt
+                _ => {
+                    logger.log(format!(
+                        "{}'s shot is blocked by {}",
+                        attacker_name.name, victim_name
+                    ));
+                    continue;
+                }
+            };
+
+            // Armour helps against arrows too
+            let defense =
+                victim_stats.defense + defense_bonus_of(victim_ent, &equipped, &defense_bonuses);
+            let dmg = i32::max(0, weapon.power as i32 - defense);
+            if dmg == 0 {
+                logger.log(format!(
+                    "{} shoots {}, but can't hurt them",
+                    attacker_name.name, victim_name
+                ));
+            } else {
+                IncomingDamage::new(&mut inc_dmg, victim_ent, dmg, Some(attacker_ent));
+                logger.log(format!(
+                    "{} shoots {} for {} dmg",
+                    attacker_name.name, victim_name, dmg
+                ));
+            }
+        }
+
+        // Clean up ranged intents from ALL entities.
+        ranged_intents.clear();
+    }
+}
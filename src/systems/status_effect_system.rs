@@ -0,0 +1,56 @@
+use crate::{
+    components::{GameplayName, IncomingDamage, MyTurn, StatusEffects, StatusKind},
+    game_log::GameLog,
+};
+use specs::prelude::*;
+
+/// Ticks down the status effects of whoever was just handed a turn.
+/// Poison and regeneration kick in here, through the damage pipeline. Poison kills are credited
+/// to whoever applied it.
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        ReadStorage<'a, MyTurn>,
+        ReadStorage<'a, GameplayName>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, IncomingDamage>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (ents, player_ent, mut logger, turns, gnames, mut statuses, mut inc_dmg) = data;
+
+        let mut cured: Vec<Entity> = Vec::new();
+        for (ent, status, _, name) in (&ents, &mut statuses, &turns, &gnames).join() {
+            for effect in status.effects.iter_mut() {
+                match effect.kind {
+                    StatusKind::Poisoned { damage } => {
+                        IncomingDamage::new(&mut inc_dmg, ent, damage, effect.source);
+                        logger.log(format!("{} suffers {} poison dmg.", name.name, damage));
+                    }
+                    StatusKind::Regenerating { heal } => {
+                        IncomingDamage::new(&mut inc_dmg, ent, -heal, None);
+                    }
+                    StatusKind::Confused | StatusKind::Hasted => {}
+                }
+
+                effect.turns -= 1;
+                if effect.turns <= 0 && ent == *player_ent {
+                    logger.log(format!("You are no longer {}.", effect.kind.describe()));
+                }
+            }
+
+            status.effects.retain(|effect| effect.turns > 0);
+            if status.effects.is_empty() {
+                cured.push(ent);
+            }
+        }
+
+        for ent in cured {
+            statuses.remove(ent);
+        }
+    }
+}
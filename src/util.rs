@@ -1,3 +1,9 @@
+pub mod bitset;
+pub mod cli;
+pub mod random_table;
 pub mod rect;
 
+pub use bitset::*;
+pub use cli::*;
+pub use random_table::*;
 pub use rect::*;
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Dense set of indices in `0..capacity`, one bit each.
+/// Meant for per-tile flags, where a HashSet would spend most of its time hashing.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct BitSet {
+    words: Vec<u64>,
+    capacity: usize,
+}
+
+impl BitSet {
+    pub fn new(capacity: usize) -> Self {
+        BitSet {
+            words: vec![0; capacity.div_ceil(WORD_BITS)],
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Panics if `idx` is out of capacity, same as indexing the tiles would
+    pub fn insert(&mut self, idx: usize) {
+        assert!(idx < self.capacity, "Index {} is out of the bitset", idx);
+        self.words[idx / WORD_BITS] |= 1 << (idx % WORD_BITS);
+    }
+
+    pub fn remove(&mut self, idx: usize) {
+        if idx < self.capacity {
+            self.words[idx / WORD_BITS] &= !(1 << (idx % WORD_BITS));
+        }
+    }
+
+    /// Indices out of capacity are never contained
+    pub fn contains(&self, idx: usize) -> bool {
+        idx < self.capacity && self.words[idx / WORD_BITS] & (1 << (idx % WORD_BITS)) != 0
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Makes this set equal to `other` without reallocating, if the capacities match
+    pub fn copy_from(&mut self, other: &BitSet) {
+        self.words.clone_from(&other.words);
+        self.capacity = other.capacity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitset_holds_what_was_inserted_until_removed_or_cleared() {
+        // Spans several words, so that the word and bit arithmetic gets exercised
+        let mut set = BitSet::new(130);
+        for idx in [0, 63, 64, 129] {
+            set.insert(idx);
+        }
+
+        assert!([0, 63, 64, 129].iter().all(|&idx| set.contains(idx)));
+        assert!(!set.contains(1) && !set.contains(62) && !set.contains(65) && !set.contains(128));
+
+        set.remove(63);
+        assert!(!set.contains(63));
+        assert!(set.contains(64));
+
+        set.clear();
+        assert!((0..set.capacity()).all(|idx| !set.contains(idx)));
+        assert_eq!(set, BitSet::new(130));
+    }
+
+    #[test]
+    fn bitset_tolerates_lookups_and_removals_out_of_capacity() {
+        let mut set = BitSet::new(10);
+        set.insert(9);
+
+        set.remove(10);
+        set.remove(1000);
+
+        assert!(!set.contains(10));
+        assert!(!set.contains(64));
+        assert!(set.contains(9));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of the bitset")]
+    fn bitset_refuses_inserts_out_of_capacity() {
+        let mut set = BitSet::new(10);
+        set.insert(10);
+    }
+
+    #[test]
+    fn bitset_copies_another_of_any_capacity() {
+        let mut source = BitSet::new(100);
+        source.insert(70);
+        let mut copy = BitSet::new(10);
+        copy.insert(3);
+
+        copy.copy_from(&source);
+
+        assert_eq!(copy, source);
+        assert_eq!(copy.capacity(), 100);
+        assert!(copy.contains(70));
+        assert!(!copy.contains(3));
+    }
+}
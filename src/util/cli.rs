@@ -0,0 +1,19 @@
+use std::env;
+
+/// Value of `--<flag> <value>` (or `--<flag>=<value>`) from the CLI args, falling back to `env_var`
+pub fn arg_or_env(flag: &str, env_var: &str) -> Option<String> {
+    let long_flag = format!("--{}", flag);
+    let long_flag_eq = format!("--{}=", flag);
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == long_flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&long_flag_eq) {
+            return Some(value.to_string());
+        }
+    }
+
+    env::var(env_var).ok()
+}
@@ -0,0 +1,47 @@
+use rltk::RandomNumberGenerator;
+
+struct RandomEntry {
+    name: String,
+    weight: i32,
+}
+
+/// Weighted random pick: an entry with weight 4 comes up twice as often as one with weight 2
+#[derive(Default)]
+pub struct RandomTable {
+    entries: Vec<RandomEntry>,
+    total_weight: i32,
+}
+
+impl RandomTable {
+    pub fn new() -> Self {
+        RandomTable::default()
+    }
+
+    /// Entries with no weight are skipped, as they could never be rolled
+    pub fn add<S: ToString>(mut self, name: S, weight: i32) -> Self {
+        if weight > 0 {
+            self.total_weight += weight;
+            self.entries.push(RandomEntry {
+                name: name.to_string(),
+                weight,
+            });
+        }
+        self
+    }
+
+    /// Rets None if the table is empty
+    pub fn roll(&self, rng: &mut RandomNumberGenerator) -> Option<String> {
+        if self.total_weight == 0 {
+            return None;
+        }
+
+        let mut roll = rng.roll_dice(1, self.total_weight) - 1;
+        for entry in self.entries.iter() {
+            if roll < entry.weight {
+                return Some(entry.name.clone());
+            }
+            roll -= entry.weight;
+        }
+        None
+    }
+}
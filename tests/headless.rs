@@ -0,0 +1,1634 @@
+use rltk::{DistanceAlg, Point, RandomNumberGenerator, VirtualKeyCode};
+use specs::prelude::*;
+use std::collections::HashMap;
+
+use rust_roguelike::components::{
+    xp_for_level, AiState, CombatStats, Companion, CompanionOrder, Equipped, Experience,
+    GameplayName, InBackpack, Initiative, MonsterAI, MonsterChar, MoveIntent, Position, Reaction,
+    StatusEffects, StatusKind, TileBlocker, Viewshed, WantsToEquip, HP_PER_LEVEL, NORMAL_SPEED,
+    POWER_PER_LEVEL,
+};
+use rust_roguelike::game_log::GameLog;
+use rust_roguelike::game_state::{orderable_companions, GameStatus, PlayerCommand};
+use rust_roguelike::gui::{self, TargetingResult};
+use rust_roguelike::headless::Simulation;
+use rust_roguelike::level::{DistanceMap, Level, TileType};
+use rust_roguelike::map_builders::{common, MapOptions, DEFAULT_MAP_HEIGHT, DEFAULT_MAP_WIDTH};
+use rust_roguelike::raws::{load_raws, Raws};
+use rust_roguelike::save_load::{self, SerializationHelper};
+use rust_roguelike::seed::RunSeed;
+use rust_roguelike::spawner;
+use rust_roguelike::systems::MovementSystem;
+use rust_roguelike::util::RandomTable;
+
+const SEED: u64 = 1234;
+/// The checkout's own raws, whatever directory the tests run from
+const RAWS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/raws/spawns.json");
+const DIRECTIONS: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (-1, -1),
+    (1, -1),
+    (-1, 1),
+];
+
+fn new_sim() -> Simulation {
+    let raws = load_raws(RAWS_PATH).expect("Raws should be valid");
+    // Pinned, so that whatever the developer's shell says doesn't change the runs
+    let map_options = MapOptions {
+        builder: Some("rooms".to_string()),
+        size: Some((DEFAULT_MAP_WIDTH, DEFAULT_MAP_HEIGHT)),
+    };
+    Simulation::new(raws, RunSeed(SEED), map_options)
+}
+
+#[test]
+fn every_builder_copes_with_the_smallest_levels() {
+    for builder in ["rooms", "bsp", "cellular", "drunkard"] {
+        for seed in 0..10 {
+            let raws = load_raws(RAWS_PATH).expect("Raws should be valid");
+            let map_options = MapOptions {
+                builder: Some(builder.to_string()),
+                size: Some((20, 20)),
+            };
+            let sim = Simulation::new(raws, RunSeed(seed), map_options);
+
+            assert_eq!(sim.status(), GameStatus::AwaitingInput);
+            let player_pos = *sim.ecs().fetch::<Point>();
+            let level = sim.ecs().fetch::<Level>();
+            let idx = level.xy_idx(player_pos.x, player_pos.y);
+            assert!(
+                level.tiles[idx] != TileType::Wall,
+                "{} with seed {}",
+                builder,
+                seed
+            );
+        }
+    }
+}
+
+#[test]
+fn room_builders_fall_back_to_a_room_when_none_fits() {
+    let mut level = Level::new(20, 20, 1);
+
+    common::ensure_a_room(&mut level);
+
+    assert_eq!(level.rooms.len(), 1);
+    let (x, y) = level.rooms[0].get_center();
+    assert!(level.tiles[level.xy_idx(x, y)] == TileType::Floor);
+}
+
+#[test]
+fn long_winding_paths_are_not_walled_off() {
+    // A corridor snaking back and forth over the whole level, well over 1000 steps long
+    let mut level = Level::new(80, 43, 1);
+    for y in (1..42).step_by(2) {
+        for x in 1..79 {
+            let idx = level.xy_idx(x, y);
+            level.tiles[idx] = TileType::Floor;
+        }
+        let turn_x = if y % 4 == 1 { 78 } else { 1 };
+        if y + 1 < 42 {
+            let idx = level.xy_idx(turn_x, y + 1);
+            level.tiles[idx] = TileType::Floor;
+        }
+    }
+    let floor_before = level
+        .tiles
+        .iter()
+        .filter(|t| **t == TileType::Floor)
+        .count();
+
+    let start_idx = level.xy_idx(1, 1);
+    common::remove_unreachable_areas_returning_most_distant(&mut level, start_idx);
+
+    let floor_after = level
+        .tiles
+        .iter()
+        .filter(|t| **t == TileType::Floor)
+        .count();
+    assert_eq!(floor_after, floor_before);
+}
+
+/// Finds a free floor tile next to the player. Rets the direction to it.
+fn free_neighbour(sim: &Simulation) -> (i32, i32) {
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let level = sim.ecs().fetch::<Level>();
+    let positions = sim.ecs().read_storage::<Position>();
+
+    for (dx, dy) in DIRECTIONS {
+        let (x, y) = (player_pos.x + dx, player_pos.y + dy);
+        let is_occupied = positions.join().any(|pos| pos.x == x && pos.y == y);
+        if level.tiles[level.xy_idx(x, y)] == TileType::Floor && !is_occupied {
+            return (dx, dy);
+        }
+    }
+    panic!("Player should have a free tile around them");
+}
+
+fn spawn_next_to_player(sim: &mut Simulation, name: &str) -> (Entity, (i32, i32)) {
+    let dir = free_neighbour(sim);
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let pos = (player_pos.x + dir.0, player_pos.y + dir.1);
+    let ent =
+        spawner::spawn_named(sim.ecs_mut(), name, pos).expect("Monster should be in the raws");
+    (ent, dir)
+}
+
+fn player_hp(sim: &Simulation) -> i32 {
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    sim.ecs()
+        .read_storage::<CombatStats>()
+        .get(player_ent)
+        .map_or(0, |stats| stats.hp)
+}
+
+#[test]
+fn starts_awaiting_input() {
+    let sim = new_sim();
+    assert_eq!(sim.status(), GameStatus::AwaitingInput);
+}
+
+#[test]
+fn same_seed_and_commands_replay_the_same_run() {
+    let script = [
+        PlayerCommand::Move { dx: 1, dy: 0 },
+        PlayerCommand::Move { dx: 0, dy: 1 },
+        PlayerCommand::Wait,
+        PlayerCommand::Move { dx: -1, dy: 0 },
+        PlayerCommand::PickUp,
+    ];
+    let mut first = new_sim();
+    let mut second = new_sim();
+    first.run_script(&script);
+    second.run_script(&script);
+
+    let first_pos = *first.ecs().fetch::<Point>();
+    let second_pos = *second.ecs().fetch::<Point>();
+    assert_eq!(first_pos, second_pos);
+    assert_eq!(player_hp(&first), player_hp(&second));
+    assert_eq!(
+        first.ecs().fetch::<GameLog>().entries,
+        second.ecs().fetch::<GameLog>().entries
+    );
+}
+
+#[test]
+fn player_melee_damages_adjacent_monster() {
+    let mut sim = new_sim();
+    let (goblin, (dx, dy)) = spawn_next_to_player(&mut sim, "Goblin");
+    sim.step(PlayerCommand::Wait); // Let the map indexing pick the goblin up
+
+    sim.step(PlayerCommand::Move { dx, dy });
+
+    let goblin_hp = sim
+        .ecs()
+        .read_storage::<CombatStats>()
+        .get(goblin)
+        .map_or(0, |stats| stats.hp);
+    assert!(
+        goblin_hp < 10,
+        "Goblin should have been hit, has {} hp",
+        goblin_hp
+    );
+}
+
+#[test]
+fn adjacent_monster_attacks_waiting_player() {
+    let mut sim = new_sim();
+    let hp_before = player_hp(&sim);
+    spawn_next_to_player(&mut sim, "Orc");
+
+    sim.step_n(PlayerCommand::Wait, 3);
+
+    assert!(player_hp(&sim) < hp_before);
+}
+
+#[test]
+fn waiting_forever_ends_in_game_over() {
+    let mut sim = new_sim();
+    for _ in 0..4 {
+        spawn_next_to_player(&mut sim, "Orc");
+    }
+
+    let status = sim.step_n(PlayerCommand::Wait, 200);
+
+    assert_eq!(status, GameStatus::GameOver);
+    assert_eq!(sim.step(PlayerCommand::Wait), GameStatus::GameOver);
+}
+
+fn count_log_entries(sim: &Simulation, prefix: &str) -> usize {
+    sim.ecs()
+        .fetch::<GameLog>()
+        .entries
+        .iter()
+        .filter(|entry| entry.starts_with(prefix))
+        .count()
+}
+
+#[test]
+fn faster_monsters_act_more_often_than_slower_ones() {
+    let mut with_goblin = new_sim();
+    spawn_next_to_player(&mut with_goblin, "Goblin");
+    with_goblin.step_n(PlayerCommand::Wait, 10);
+
+    let mut with_zombie = new_sim();
+    spawn_next_to_player(&mut with_zombie, "Zombie");
+    with_zombie.step_n(PlayerCommand::Wait, 10);
+
+    // Goblins are 1.3x and zombies 0.5x as fast as the player
+    let goblin_attacks = count_log_entries(&with_goblin, "Goblin hits");
+    let zombie_attacks = count_log_entries(&with_zombie, "Zombie hits");
+    assert!(
+        goblin_attacks >= 12,
+        "Goblin attacked {} times",
+        goblin_attacks
+    );
+    assert!(
+        zombie_attacks <= 6,
+        "Zombie attacked {} times",
+        zombie_attacks
+    );
+}
+
+#[test]
+fn actors_no_ai_handles_do_not_stall_the_turns() {
+    let mut sim = new_sim();
+    // E.g. from a partial template: it gets turns, but nothing ever acts on them
+    sim.ecs_mut()
+        .create_entity()
+        .with(Initiative::new(NORMAL_SPEED))
+        .build();
+
+    // A stall never gets back to asking for input
+    let status = sim.step_n(PlayerCommand::Wait, 5);
+
+    assert_eq!(status, GameStatus::AwaitingInput);
+}
+
+/// Floor tile the player sees, `min..=max` tiles away, with nothing in the way
+fn tile_in_clear_shot(sim: &Simulation, min: f32, max: f32) -> (i32, i32) {
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let level = sim.ecs().fetch::<Level>();
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    let viewsheds = sim.ecs().read_storage::<Viewshed>();
+    let vs = viewsheds
+        .get(player_ent)
+        .expect("Player should have a viewshed");
+
+    vs.visible_tiles
+        .iter()
+        .find(|p| {
+            let dist = DistanceAlg::Pythagoras.distance2d(**p, player_pos);
+            let idx = level.xy_idx(p.x, p.y);
+            (min..=max).contains(&dist)
+                && level.tiles[idx] == TileType::Floor
+                && level.tile_content[idx].is_empty()
+                && level.has_clear_shot(**p, player_pos)
+        })
+        .map(|p| (p.x, p.y))
+        .expect("Player should see some floor in a clear shot")
+}
+
+fn distance_to_player(sim: &Simulation, ent: Entity) -> f32 {
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let positions = sim.ecs().read_storage::<Position>();
+    let pos = positions.get(ent).expect("Entity should have a position");
+    DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), player_pos)
+}
+
+#[test]
+fn archer_shoots_from_a_distance() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let hp_before = player_hp(&sim);
+    let pos = tile_in_clear_shot(&sim, 3.0, 5.0);
+    let archer = spawner::spawn_named(sim.ecs_mut(), "Goblin Archer", pos)
+        .expect("Goblin Archer should be in the raws");
+
+    sim.step_n(PlayerCommand::Wait, 3);
+
+    assert!(count_log_entries(&sim, "Goblin Archer shoots Player") > 0);
+    assert!(player_hp(&sim) < hp_before);
+    assert!(distance_to_player(&sim, archer) >= 3.0);
+}
+
+#[test]
+fn archer_backs_off_when_approached() {
+    let mut sim = new_sim();
+    let (archer, _) = spawn_next_to_player(&mut sim, "Goblin Archer");
+    sim.step(PlayerCommand::Wait); // Let the archer see the player
+
+    sim.step_n(PlayerCommand::Wait, 2);
+
+    assert!(distance_to_player(&sim, archer) > 1.5);
+}
+
+#[test]
+fn player_shoots_the_nearest_target() {
+    let mut sim = new_sim();
+    let sling = carry(&mut sim, "Sling");
+    sim.step(PlayerCommand::Equip(sling));
+    let pos = tile_in_clear_shot(&sim, 2.0, 4.0);
+    let zombie =
+        spawner::spawn_named(sim.ecs_mut(), "Zombie", pos).expect("Zombie should be in the raws");
+
+    let targets = gui::targets_in_range(sim.ecs(), 6);
+    assert_eq!(targets.first(), Some(&Point::new(pos.0, pos.1)));
+
+    sim.step(PlayerCommand::Shoot { target: targets[0] });
+
+    let zombie_stats = sim.ecs().read_storage::<CombatStats>();
+    let zombie_stats = zombie_stats
+        .get(zombie)
+        .expect("Zombie should survive one shot");
+    assert!(zombie_stats.hp < zombie_stats.max_hp as i32);
+    assert_eq!(count_log_entries(&sim, "Player shoots Zombie"), 1);
+}
+
+#[test]
+fn player_needs_a_worn_sling_to_shoot() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let pos = tile_in_clear_shot(&sim, 2.0, 4.0);
+    spawner::spawn_named(sim.ecs_mut(), "Zombie", pos).expect("Zombie should be in the raws");
+    let target = Point::new(pos.0, pos.1);
+    let sling = carry(&mut sim, "Sling");
+
+    sim.step(PlayerCommand::Shoot { target });
+    sim.step(PlayerCommand::Equip(sling));
+    sim.step(PlayerCommand::Unequip(sling));
+    sim.step(PlayerCommand::Shoot { target });
+
+    assert_eq!(
+        count_log_entries(&sim, "Player has nothing to shoot with."),
+        2
+    );
+    assert_eq!(count_log_entries(&sim, "Player shoots"), 0);
+}
+
+#[test]
+fn targeting_fires_only_at_the_previewed_cursor() {
+    let sim = new_sim();
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let (x, y) = tile_in_clear_shot(&sim, 2.0, 4.0);
+    let tile = Point::new(x, y);
+
+    // A click away from the cursor only moves the cursor, and with it the preview
+    let moved = gui::targeting_input(sim.ecs(), 6, player_pos, None, Some(tile));
+    assert_eq!(moved, TargetingResult::NoResponse { cursor: tile });
+
+    // Whichever way it's confirmed, the shot goes where the cursor was drawn
+    let entered = gui::targeting_input(sim.ecs(), 6, tile, Some(VirtualKeyCode::Return), None);
+    let clicked = gui::targeting_input(sim.ecs(), 6, tile, None, Some(tile));
+    assert_eq!(entered, TargetingResult::Selected { target: tile });
+    assert_eq!(clicked, TargetingResult::Selected { target: tile });
+}
+
+/// Puts an item from the raws into the player's backpack. Takes a turn.
+fn carry(sim: &mut Simulation, name: &str) -> Entity {
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let item = spawner::spawn_named(sim.ecs_mut(), name, (player_pos.x, player_pos.y))
+        .expect("Item should be in the raws");
+    sim.step(PlayerCommand::PickUp);
+    item
+}
+
+fn hp_of(sim: &Simulation, ent: Entity) -> i32 {
+    sim.ecs()
+        .read_storage::<CombatStats>()
+        .get(ent)
+        .expect("Entity should have combat stats")
+        .hp
+}
+
+#[test]
+fn magic_missile_scroll_damages_its_target_and_is_used_up() {
+    let mut sim = new_sim();
+    let scroll = carry(&mut sim, "Magic Missile Scroll");
+    let pos = tile_in_clear_shot(&sim, 2.0, 4.0);
+    let zombie =
+        spawner::spawn_named(sim.ecs_mut(), "Zombie", pos).expect("Zombie should be in the raws");
+    let hp_before = hp_of(&sim, zombie);
+
+    sim.step(PlayerCommand::UseItem {
+        item: scroll,
+        target: Some(Point::new(pos.0, pos.1)),
+    });
+
+    assert_eq!(hp_of(&sim, zombie), hp_before - 8);
+    assert!(!sim.ecs().is_alive(scroll));
+}
+
+#[test]
+fn fireball_scroll_hits_everyone_in_the_area() {
+    let mut sim = new_sim();
+    let scroll = carry(&mut sim, "Fireball Scroll");
+    let pos = tile_in_clear_shot(&sim, 1.5, 3.0);
+    let zombie =
+        spawner::spawn_named(sim.ecs_mut(), "Zombie", pos).expect("Zombie should be in the raws");
+    let player_hp_before = player_hp(&sim);
+    let player_pos = *sim.ecs().fetch::<Point>();
+
+    // Right on top of the player, so the blast reaches both
+    sim.step(PlayerCommand::UseItem {
+        item: scroll,
+        target: Some(player_pos),
+    });
+
+    assert!(hp_of(&sim, zombie) < 24);
+    assert!(player_hp(&sim) < player_hp_before);
+}
+
+#[test]
+fn inventory_lists_no_more_stacks_than_there_are_letters() {
+    let mut sim = new_sim();
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    for i in 0..gui::MAX_MENU_ENTRIES + 4 {
+        sim.ecs_mut()
+            .create_entity()
+            .with(GameplayName {
+                name: format!("Trinket {}", i),
+            })
+            .with(InBackpack { owner: player_ent })
+            .build();
+    }
+
+    assert_eq!(
+        gui::inventory_stacks(sim.ecs()).len(),
+        gui::MAX_MENU_ENTRIES
+    );
+}
+
+#[test]
+fn items_out_of_reach_or_not_carried_are_not_used() {
+    let mut sim = new_sim();
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let scroll = carry(&mut sim, "Magic Missile Scroll");
+
+    sim.step(PlayerCommand::UseItem {
+        item: scroll,
+        target: Some(Point::new(player_pos.x + 30, player_pos.y)),
+    });
+    assert!(sim.ecs().is_alive(scroll));
+    assert_eq!(
+        count_log_entries(&sim, "The Magic Missile Scroll can't reach that far"),
+        1
+    );
+
+    // Lying on the floor rather than in the backpack
+    let on_floor = spawner::spawn_named(
+        sim.ecs_mut(),
+        "Fireball Scroll",
+        (player_pos.x, player_pos.y),
+    )
+    .expect("Item should be in the raws");
+    sim.step(PlayerCommand::UseItem {
+        item: on_floor,
+        target: Some(player_pos),
+    });
+    assert!(sim.ecs().is_alive(on_floor));
+    assert_eq!(
+        count_log_entries(&sim, "Player doesn't carry the Fireball Scroll"),
+        1
+    );
+}
+
+#[test]
+fn confused_monster_stumbles_instead_of_attacking() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let scroll = carry(&mut sim, "Confusion Scroll");
+    let (_, (dx, dy)) = spawn_next_to_player(&mut sim, "Orc");
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let hp_before = player_hp(&sim);
+
+    sim.step(PlayerCommand::UseItem {
+        item: scroll,
+        target: Some(Point::new(player_pos.x + dx, player_pos.y + dy)),
+    });
+    sim.step_n(PlayerCommand::Wait, 2);
+
+    assert_eq!(count_log_entries(&sim, "Orc is confused"), 1);
+    assert_eq!(player_hp(&sim), hp_before);
+}
+
+#[test]
+fn poison_wears_off_after_dealing_damage_every_turn() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let dart = carry(&mut sim, "Poison Dart");
+    let pos = tile_in_clear_shot(&sim, 2.0, 4.0);
+    let orc = spawner::spawn_named(sim.ecs_mut(), "Orc", pos).expect("Orc should be in the raws");
+
+    sim.step(PlayerCommand::UseItem {
+        item: dart,
+        target: Some(Point::new(pos.0, pos.1)),
+    });
+    sim.step_n(PlayerCommand::Wait, 8);
+
+    // 1 from the dart itself, then 2 on each of the 5 poisoned turns
+    assert_eq!(hp_of(&sim, orc), 16 - 1 - 5 * 2);
+    assert!(sim.ecs().read_storage::<StatusEffects>().get(orc).is_none());
+}
+
+#[test]
+fn regeneration_heals_the_player_over_time() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let potion = carry(&mut sim, "Regeneration Potion");
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    if let Some(stats) = sim
+        .ecs_mut()
+        .write_storage::<CombatStats>()
+        .get_mut(player_ent)
+    {
+        stats.hp = 10;
+    }
+
+    sim.step(PlayerCommand::UseItem {
+        item: potion,
+        target: None,
+    });
+    {
+        let statuses = sim.ecs().read_storage::<StatusEffects>();
+        let status = statuses
+            .get(player_ent)
+            .expect("Player should be under the potion's effect");
+        assert!(status.has(StatusKind::Regenerating { heal: 1 }));
+    }
+    sim.step_n(PlayerCommand::Wait, 5);
+
+    assert!(player_hp(&sim) > 10);
+}
+
+fn worn_by_player(sim: &Simulation) -> Vec<Entity> {
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    let entities = sim.ecs().entities();
+    let equipped = sim.ecs().read_storage::<Equipped>();
+    (&entities, &equipped)
+        .join()
+        .filter(|(_, worn)| worn.owner == player_ent)
+        .map(|(ent, _)| ent)
+        .collect()
+}
+
+#[test]
+fn equipped_weapon_adds_to_melee_damage() {
+    let mut sim = new_sim();
+    let dagger = carry(&mut sim, "Dagger");
+    sim.step(PlayerCommand::Equip(dagger));
+    let (_, (dx, dy)) = spawn_next_to_player(&mut sim, "Zombie");
+    sim.step(PlayerCommand::Wait); // Let the map indexing pick the zombie up
+
+    sim.step(PlayerCommand::Move { dx, dy });
+
+    // Player power 5 + 2 from the dagger, against the zombie's defense of 1
+    assert_eq!(count_log_entries(&sim, "Player hits Zombie for 6 dmg"), 1);
+}
+
+#[test]
+fn equipping_into_an_occupied_slot_swaps_the_items() {
+    let mut sim = new_sim();
+    let dagger = carry(&mut sim, "Dagger");
+    let sword = carry(&mut sim, "Longsword");
+    let shield = carry(&mut sim, "Shield");
+
+    sim.run_script(&[
+        PlayerCommand::Equip(dagger),
+        PlayerCommand::Equip(shield),
+        PlayerCommand::Equip(sword),
+    ]);
+    let mut worn = worn_by_player(&sim);
+    worn.sort();
+    let mut expected = vec![sword, shield];
+    expected.sort();
+    assert_eq!(worn, expected);
+
+    sim.run_script(&[
+        PlayerCommand::Unequip(shield),
+        PlayerCommand::DropItem(sword),
+    ]);
+    assert!(worn_by_player(&sim).is_empty());
+}
+
+#[test]
+fn only_carried_items_are_put_on_and_only_by_their_wearer_taken_off() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let shield = spawner::spawn_named(sim.ecs_mut(), "Shield", (player_pos.x, player_pos.y))
+        .expect("Shield should be in the raws");
+
+    sim.step(PlayerCommand::Equip(shield));
+    assert!(worn_by_player(&sim).is_empty());
+    assert!(sim.ecs().read_storage::<Position>().contains(shield));
+
+    // Handed over to an orc, who puts it on
+    let (orc, _) = spawn_next_to_player(&mut sim, "Orc");
+    sim.ecs_mut().write_storage::<Position>().remove(shield);
+    sim.ecs_mut()
+        .write_storage::<InBackpack>()
+        .insert(shield, InBackpack { owner: orc })
+        .expect("Should be able to hand the shield over");
+    sim.ecs_mut()
+        .write_storage::<WantsToEquip>()
+        .insert(orc, WantsToEquip { item: shield })
+        .expect("Should be able to insert an equip intent");
+    sim.step(PlayerCommand::Wait);
+
+    sim.step(PlayerCommand::Unequip(shield));
+    let equipped = sim.ecs().read_storage::<Equipped>();
+    assert_eq!(equipped.get(shield).map(|worn| worn.owner), Some(orc));
+}
+
+#[test]
+fn only_carried_items_are_dropped_and_only_by_their_carrier() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let (orc, (dx, dy)) = spawn_next_to_player(&mut sim, "Orc");
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let floor_pos = Position {
+        x: player_pos.x + dx,
+        y: player_pos.y + dy,
+    };
+    let dagger = spawner::spawn_named(sim.ecs_mut(), "Dagger", (floor_pos.x, floor_pos.y))
+        .expect("Dagger should be in the raws");
+    let shield = carry(&mut sim, "Shield");
+    sim.step(PlayerCommand::Equip(shield));
+    // Handed over to the orc, who keeps wearing it
+    sim.ecs_mut()
+        .write_storage::<InBackpack>()
+        .insert(shield, InBackpack { owner: orc })
+        .expect("Should be able to hand the shield over");
+    sim.ecs_mut()
+        .write_storage::<Equipped>()
+        .get_mut(shield)
+        .expect("Shield should be worn")
+        .owner = orc;
+
+    sim.step(PlayerCommand::DropItem(dagger));
+    sim.step(PlayerCommand::DropItem(shield));
+
+    let positions = sim.ecs().read_storage::<Position>();
+    let dagger_pos = positions
+        .get(dagger)
+        .expect("Dagger should stay on the floor");
+    assert_eq!((dagger_pos.x, dagger_pos.y), (floor_pos.x, floor_pos.y));
+    assert!(!positions.contains(shield));
+    let equipped = sim.ecs().read_storage::<Equipped>();
+    assert_eq!(equipped.get(shield).map(|worn| worn.owner), Some(orc));
+    assert_eq!(count_log_entries(&sim, "Player doesn't carry the"), 2);
+}
+
+#[test]
+fn health_potions_heal_up_to_the_max_and_are_used_up() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    let max_hp = {
+        let mut stats = sim.ecs_mut().write_storage::<CombatStats>();
+        let stats = stats
+            .get_mut(player_ent)
+            .expect("Player should have combat stats");
+        stats.hp = stats.max_hp as i32 - 10;
+        stats.max_hp as i32
+    };
+    let first = carry(&mut sim, "Health Potion");
+    let second = carry(&mut sim, "Health Potion");
+
+    sim.step(PlayerCommand::UseItem {
+        item: first,
+        target: None,
+    });
+    // Potion heals 8
+    assert_eq!(hp_of(&sim, player_ent), max_hp - 2);
+    assert!(!sim.ecs().is_alive(first));
+
+    sim.step(PlayerCommand::UseItem {
+        item: second,
+        target: None,
+    });
+    assert_eq!(hp_of(&sim, player_ent), max_hp);
+    assert!(!sim.ecs().is_alive(second));
+}
+
+/// Hits the monster until it dies, giving up after a while
+fn kill_adjacent(sim: &mut Simulation, monster: Entity, (dx, dy): (i32, i32)) {
+    for _ in 0..10 {
+        if !sim.ecs().is_alive(monster) {
+            return;
+        }
+        sim.step(PlayerCommand::Move { dx, dy });
+    }
+    panic!("Monster should have died by now");
+}
+
+fn player_experience(sim: &Simulation) -> Experience {
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    sim.ecs()
+        .read_storage::<Experience>()
+        .get(player_ent)
+        .cloned()
+        .expect("Player should have experience")
+}
+
+#[test]
+fn killing_a_monster_awards_its_xp_to_the_killer() {
+    let mut sim = new_sim();
+    let (goblin, dir) = spawn_next_to_player(&mut sim, "Goblin");
+    sim.step(PlayerCommand::Wait); // Let the map indexing pick the goblin up
+
+    kill_adjacent(&mut sim, goblin, dir);
+
+    assert_eq!(player_experience(&sim).xp, 5);
+    assert_eq!(count_log_entries(&sim, "You gain 5 xp"), 1);
+}
+
+#[test]
+fn poison_kills_award_xp_to_whoever_applied_it() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let dart = carry(&mut sim, "Poison Dart");
+    let pos = tile_in_clear_shot(&sim, 2.0, 4.0);
+    let orc = spawner::spawn_named(sim.ecs_mut(), "Orc", pos).expect("Orc should be in the raws");
+    if let Some(stats) = sim.ecs_mut().write_storage::<CombatStats>().get_mut(orc) {
+        stats.hp = 4;
+    }
+
+    sim.step(PlayerCommand::UseItem {
+        item: dart,
+        target: Some(Point::new(pos.0, pos.1)),
+    });
+    sim.step_n(PlayerCommand::Wait, 4);
+
+    assert!(!sim.ecs().is_alive(orc));
+    assert!(player_experience(&sim).xp > 0);
+}
+
+#[test]
+fn reaching_the_xp_threshold_levels_the_player_up() {
+    let mut sim = new_sim();
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    if let Some(exp) = sim
+        .ecs_mut()
+        .write_storage::<Experience>()
+        .get_mut(player_ent)
+    {
+        exp.xp = xp_for_level(2) - 1;
+    }
+    let (goblin, dir) = spawn_next_to_player(&mut sim, "Goblin");
+    sim.step(PlayerCommand::Wait); // Let the map indexing pick the goblin up
+
+    kill_adjacent(&mut sim, goblin, dir);
+
+    assert_eq!(player_experience(&sim).level, 2);
+    let stats = sim
+        .ecs()
+        .read_storage::<CombatStats>()
+        .get(player_ent)
+        .cloned()
+        .expect("Player should have combat stats");
+    assert_eq!(stats.max_hp, 30 + HP_PER_LEVEL);
+    assert_eq!(stats.power, 5 + POWER_PER_LEVEL);
+}
+
+fn ai_of(sim: &Simulation, ent: Entity) -> MonsterAI {
+    sim.ecs()
+        .read_storage::<MonsterAI>()
+        .get(ent)
+        .cloned()
+        .expect("Monster should have an AI")
+}
+
+fn position_of(sim: &Simulation, ent: Entity) -> Point {
+    let positions = sim.ecs().read_storage::<Position>();
+    let pos = positions.get(ent).expect("Entity should have a position");
+    Point::new(pos.x, pos.y)
+}
+
+/// Moves the player onto the given tile
+fn place_player(sim: &mut Simulation, x: i32, y: i32) {
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    if let Some(pos) = sim
+        .ecs_mut()
+        .write_storage::<Position>()
+        .get_mut(player_ent)
+    {
+        (pos.x, pos.y) = (x, y);
+    }
+    if let Some(vs) = sim
+        .ecs_mut()
+        .write_storage::<Viewshed>()
+        .get_mut(player_ent)
+    {
+        vs.is_dirty = true;
+    }
+    *sim.ecs_mut().write_resource::<Point>() = Point::new(x, y);
+}
+
+/// Moves the player onto the floor tile farthest from `away_from`
+fn teleport_player_away(sim: &mut Simulation, away_from: Point) {
+    let target = {
+        let level = sim.ecs().fetch::<Level>();
+        (0..level.tiles.len())
+            .filter(|&idx| level.tiles[idx] == TileType::Floor)
+            .map(|idx| level.idx_xy(idx))
+            .max_by_key(|&(x, y)| (x - away_from.x).pow(2) + (y - away_from.y).pow(2))
+            .expect("Level should have some floor")
+    };
+
+    place_player(sim, target.0, target.1);
+}
+
+#[test]
+fn monster_searches_where_it_last_saw_the_player() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let (orc, _) = spawn_next_to_player(&mut sim, "Orc");
+    sim.step(PlayerCommand::Wait);
+    let seen_at = *sim.ecs().fetch::<Point>();
+    assert_eq!(ai_of(&sim, orc).state, AiState::Hunting);
+    assert_eq!(ai_of(&sim, orc).last_seen, Some(seen_at));
+
+    let orc_pos = position_of(&sim, orc);
+    teleport_player_away(&mut sim, orc_pos);
+    let mut history = Vec::new();
+    for _ in 0..4 {
+        sim.step(PlayerCommand::Wait);
+        history.push((ai_of(&sim, orc).state, position_of(&sim, orc)));
+    }
+
+    assert!(history
+        .iter()
+        .any(|(state, _)| *state == AiState::Searching));
+    assert!(history.iter().any(|(_, pos)| *pos == seen_at));
+    // Nobody was there, so the orc forgets about it
+    assert_eq!(ai_of(&sim, orc).state, AiState::Idle);
+    assert_eq!(ai_of(&sim, orc).last_seen, None);
+}
+
+#[test]
+fn badly_hurt_monster_flees() {
+    let mut sim = new_sim();
+    let (orc, _) = spawn_next_to_player(&mut sim, "Orc");
+    if let Some(stats) = sim.ecs_mut().write_storage::<CombatStats>().get_mut(orc) {
+        stats.hp = 2;
+    }
+
+    sim.step_n(PlayerCommand::Wait, 2);
+
+    assert_eq!(ai_of(&sim, orc).state, AiState::Fleeing);
+    assert!(distance_to_player(&sim, orc) > 1.5);
+}
+
+#[test]
+fn distance_maps_lead_towards_and_away_from_the_goal() {
+    // A straight corridor along y = 1
+    let mut level = Level::new(12, 3, 1);
+    for x in 1..11 {
+        let idx = level.xy_idx(x, 1);
+        level.tiles[idx] = TileType::Floor;
+    }
+    level.index_walls();
+    let goal = level.xy_idx(4, 1);
+
+    let to_goal = DistanceMap::new(&level, &[goal]);
+    let away = to_goal.flee_map(&level);
+
+    assert_eq!(to_goal.value(goal), 0.0);
+    assert_eq!(to_goal.value(level.xy_idx(9, 1)), 5.0);
+    assert_eq!(to_goal.value(level.xy_idx(4, 0)), DistanceMap::UNREACHABLE);
+    assert_eq!(
+        to_goal.downhill_step(&level, level.xy_idx(8, 1)),
+        Some(level.xy_idx(7, 1))
+    );
+    assert_eq!(to_goal.downhill_step(&level, goal), None);
+    assert_eq!(
+        away.downhill_step(&level, level.xy_idx(6, 1)),
+        Some(level.xy_idx(7, 1))
+    );
+    // Cornered at the end of the corridor
+    assert_eq!(away.downhill_step(&level, level.xy_idx(10, 1)), None);
+}
+
+#[test]
+fn monsters_close_in_along_the_distance_map() {
+    let mut sim = new_sim();
+    open_field(&mut sim);
+    let orc = spawn_orc_at(&mut sim, 15, 15);
+    let distance_left = |sim: &Simulation| {
+        let level = sim.ecs().fetch::<Level>();
+        let pos = position_of(sim, orc);
+        level.to_player.value(level.xy_idx(pos.x, pos.y))
+    };
+    sim.step(PlayerCommand::Wait);
+    let before = distance_left(&sim);
+
+    sim.step(PlayerCommand::Wait);
+
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let level = sim.ecs().fetch::<Level>();
+    assert_eq!(
+        level
+            .to_player
+            .value(level.xy_idx(player_pos.x, player_pos.y)),
+        0.0
+    );
+    assert!(distance_left(&sim) < before);
+}
+
+fn tile_blocker_positions(sim: &Simulation) -> Vec<Point> {
+    let positions = sim.ecs().read_storage::<Position>();
+    let blockers = sim.ecs().read_storage::<TileBlocker>();
+    (&positions, &blockers)
+        .join()
+        .map(|(pos, _)| Point::new(pos.x, pos.y))
+        .collect()
+}
+
+#[test]
+fn tile_blockers_never_share_a_tile() {
+    let mut sim = new_sim();
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    if let Some(stats) = sim
+        .ecs_mut()
+        .write_storage::<CombatStats>()
+        .get_mut(player_ent)
+    {
+        stats.max_hp = 10_000;
+        stats.hp = 10_000;
+    }
+    // A crowd closing in from all around the player
+    for name in [
+        "Orc", "Goblin", "Orc", "Goblin", "Orc", "Goblin", "Orc", "Goblin",
+    ] {
+        let pos = tile_in_clear_shot(&sim, 2.0, 6.0);
+        spawner::spawn_named(sim.ecs_mut(), name, pos).expect("Monster should be in the raws");
+        sim.step(PlayerCommand::Wait);
+    }
+
+    for _ in 0..30 {
+        sim.step(PlayerCommand::Wait);
+
+        let mut occupied = tile_blocker_positions(&sim);
+        let count = occupied.len();
+        occupied.sort_by_key(|p| (p.x, p.y));
+        occupied.dedup();
+        assert_eq!(occupied.len(), count, "Two tile blockers share a tile");
+    }
+}
+
+/// Size of the open field, so that the fixtures' coordinates are always on it
+const FIELD_WIDTH: i32 = 60;
+const FIELD_HEIGHT: i32 = 30;
+
+/// Swaps the level for an open field, so that moves can be set up wherever.
+/// Whatever stood on the old level is removed, the player is put at (10, 15),
+/// out of the way of the fixtures.
+fn open_field(sim: &mut Simulation) {
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    let placed: Vec<Entity> = {
+        let entities = sim.ecs().entities();
+        let positions = sim.ecs().read_storage::<Position>();
+        (&entities, &positions)
+            .join()
+            .map(|(ent, _)| ent)
+            .filter(|&ent| ent != player_ent)
+            .collect()
+    };
+    sim.ecs_mut()
+        .delete_entities(&placed)
+        .expect("Should be able to delete the old level's entities");
+    sim.ecs_mut().maintain();
+
+    let mut level = Level::new(FIELD_WIDTH, FIELD_HEIGHT, 1);
+    for y in 1..FIELD_HEIGHT - 1 {
+        for x in 1..FIELD_WIDTH - 1 {
+            let idx = level.xy_idx(x, y);
+            level.tiles[idx] = TileType::Floor;
+        }
+    }
+    level.index_walls();
+    sim.ecs_mut().insert(level);
+    place_player(sim, 10, 15);
+}
+
+fn spawn_orc_at(sim: &mut Simulation, x: i32, y: i32) -> Entity {
+    spawner::spawn_named(sim.ecs_mut(), "Orc", (x, y)).expect("Orc should be in the raws")
+}
+
+fn intend_move(sim: &mut Simulation, ent: Entity, x: i32, y: i32) {
+    sim.ecs_mut()
+        .write_storage::<MoveIntent>()
+        .insert(
+            ent,
+            MoveIntent {
+                destination: Point::new(x, y),
+            },
+        )
+        .expect("Should be able to insert a move intent");
+}
+
+#[test]
+fn movement_swaps_blockers_stepping_onto_each_other() {
+    let mut sim = new_sim();
+    open_field(&mut sim);
+    let a = spawn_orc_at(&mut sim, 40, 20);
+    let b = spawn_orc_at(&mut sim, 41, 20);
+    intend_move(&mut sim, a, 41, 20);
+    intend_move(&mut sim, b, 40, 20);
+
+    MovementSystem {}.run_now(sim.ecs());
+
+    assert_eq!(position_of(&sim, a), Point::new(41, 20));
+    assert_eq!(position_of(&sim, b), Point::new(40, 20));
+}
+
+#[test]
+fn movement_lets_one_of_the_contenders_through() {
+    let mut sim = new_sim();
+    open_field(&mut sim);
+    let a = spawn_orc_at(&mut sim, 40, 20);
+    let b = spawn_orc_at(&mut sim, 42, 20);
+    intend_move(&mut sim, a, 41, 20);
+    intend_move(&mut sim, b, 41, 20);
+
+    MovementSystem {}.run_now(sim.ecs());
+
+    // The first in entity order wins, the other one waits. Ids get reused, so that needn't be `a`.
+    let (winner, loser, loser_from) = if a.id() < b.id() {
+        (a, b, Point::new(42, 20))
+    } else {
+        (b, a, Point::new(40, 20))
+    };
+    assert_eq!(position_of(&sim, winner), Point::new(41, 20));
+    assert_eq!(position_of(&sim, loser), loser_from);
+}
+
+#[test]
+fn movement_follows_blockers_moving_away_and_waits_on_those_staying() {
+    let mut sim = new_sim();
+    open_field(&mut sim);
+    let follower = spawn_orc_at(&mut sim, 40, 20);
+    let leader = spawn_orc_at(&mut sim, 41, 20);
+    let blocked = spawn_orc_at(&mut sim, 40, 25);
+    let _stayer = spawn_orc_at(&mut sim, 41, 25);
+    intend_move(&mut sim, follower, 41, 20);
+    intend_move(&mut sim, leader, 42, 20);
+    intend_move(&mut sim, blocked, 41, 25);
+
+    MovementSystem {}.run_now(sim.ecs());
+
+    assert_eq!(position_of(&sim, follower), Point::new(41, 20));
+    assert_eq!(position_of(&sim, leader), Point::new(42, 20));
+    assert_eq!(position_of(&sim, blocked), Point::new(40, 25));
+    assert!(sim.ecs().read_storage::<MoveIntent>().is_empty());
+}
+
+#[test]
+fn factions_react_to_each_other_as_the_raws_say() {
+    let raws = load_raws(RAWS_PATH).expect("Raws should be valid");
+
+    assert_eq!(raws.reaction("orcs", "goblins"), Reaction::Hostile);
+    assert_eq!(raws.reaction("orcs", "player"), Reaction::Hostile);
+    assert_eq!(raws.reaction("orcs", "orcs"), Reaction::Friendly);
+    assert_eq!(raws.reaction("orcs", "critters"), Reaction::Neutral);
+    assert_eq!(raws.reaction("critters", "player"), Reaction::Neutral);
+}
+
+/// Loads raws written on the fly, `name` keeps the temp files of different tests apart
+fn raws_from_json(name: &str, json: &str) -> Result<Raws, String> {
+    let path = std::env::temp_dir().join(format!("{}_{}.json", name, std::process::id()));
+    std::fs::write(&path, json).expect("Should be able to write the temp file");
+    let raws = load_raws(&path);
+    std::fs::remove_file(&path).expect("Should be able to remove the temp file");
+    raws
+}
+
+/// How often each name comes up in `rolls` rolls of the table
+fn roll_counts(table: &RandomTable, rolls: usize) -> HashMap<String, usize> {
+    let mut rng = RandomNumberGenerator::seeded(SEED);
+    let mut counts = HashMap::new();
+    for _ in 0..rolls {
+        let name = table.roll(&mut rng).expect("Table shouldn't be empty");
+        *counts.entry(name).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[test]
+fn spawn_tables_follow_the_depth() {
+    let raws = raws_from_json(
+        "depth_spawns",
+        r##"{
+            "monsters": [],
+            "companions": [],
+            "items": [
+                { "name": "Early", "glyph": "!", "fg": "#FFFFFF" },
+                { "name": "Late", "glyph": "!", "fg": "#FFFFFF" },
+                { "name": "Always", "glyph": "!", "fg": "#FFFFFF" }
+            ],
+            "spawn_table": [
+                { "name": "Early", "weight": 5, "min_depth": 1, "max_depth": 2 },
+                { "name": "Late", "weight": 1, "min_depth": 3, "extra_weight_per_depth": 10 },
+                { "name": "Always", "weight": 5, "min_depth": 1 }
+            ],
+            "factions": [{ "name": "player", "default": "hostile" }]
+        }"##,
+    )
+    .expect("Raws should be valid");
+
+    let shallow = roll_counts(&raws.item_table(1), 1000);
+    let middle = roll_counts(&raws.item_table(3), 1000);
+    let deep = roll_counts(&raws.item_table(6), 1000);
+
+    // Out of their depth range, entries never come up
+    assert!(!shallow.contains_key("Late"));
+    assert!(shallow.contains_key("Early"));
+    assert!(!middle.contains_key("Early") && !deep.contains_key("Early"));
+    // Late weighs 1 against 5 at its min depth, and 31 against 5 three levels below
+    assert!(middle["Late"] < middle["Always"]);
+    assert!(deep["Late"] > deep["Always"] * 3);
+}
+
+/// Small raws made of the given `monsters`, `factions` and `spawn_table`. Tests run in parallel,
+/// so each call needs a `name` of its own.
+fn raws_with(
+    name: &str,
+    monsters: &str,
+    factions: &str,
+    spawn_table: &str,
+) -> Result<Raws, String> {
+    let json = format!(
+        r##"{{
+            "monsters": {},
+            "companions": [],
+            "items": [{{ "name": "Potion", "glyph": "!", "fg": "#FF00FF", "healing": 5 }}],
+            "spawn_table": {},
+            "factions": {}
+        }}"##,
+        monsters, spawn_table, factions
+    );
+    raws_from_json(name, &json)
+}
+
+const ORC_RAW: &str = r##"{ "name": "Orc", "glyph": "o", "fg": "#FF0000",
+    "stats": { "max_hp": 10, "defense": 1, "power": 3 }, "vision_range": 8, "faction": "orcs" }"##;
+const FACTIONS_RAW: &str = r##"[{ "name": "player", "default": "hostile" },
+    { "name": "orcs", "default": "hostile" }]"##;
+const SPAWN_TABLE_RAW: &str = r##"[{ "name": "Orc", "weight": 1, "min_depth": 1 }]"##;
+
+#[test]
+fn raws_load_when_valid() {
+    let raws = raws_with(
+        "valid_raws",
+        &format!("[{}]", ORC_RAW),
+        FACTIONS_RAW,
+        SPAWN_TABLE_RAW,
+    );
+
+    assert!(raws.is_ok(), "{:?}", raws.err());
+}
+
+#[test]
+fn raws_reject_malformed_json() {
+    let err = raws_from_json("malformed_raws", r#"{ "monsters": [ "#)
+        .expect_err("Truncated raws should be rejected");
+
+    assert!(err.contains("malformed_raws"), "{}", err);
+}
+
+#[test]
+fn raws_reject_duplicate_names() {
+    let monsters = format!("[{}, {}]", ORC_RAW, ORC_RAW);
+
+    let err = raws_with(
+        "duplicate_names_raws",
+        &monsters,
+        FACTIONS_RAW,
+        SPAWN_TABLE_RAW,
+    )
+    .expect_err("Duplicate names should be rejected");
+
+    assert!(
+        err.contains(r#"monsters[1] ("Orc"): name is already taken"#),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn raws_reject_unknown_factions() {
+    let monsters = format!("[{}]", ORC_RAW.replace(r#""orcs""#, r#""elves""#));
+
+    let err = raws_with(
+        "unknown_faction_raws",
+        &monsters,
+        FACTIONS_RAW,
+        SPAWN_TABLE_RAW,
+    )
+    .expect_err("Unknown factions should be rejected");
+
+    assert!(
+        err.contains(r#"there is no faction named "elves""#),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn raws_reject_bad_reactions() {
+    let orcs = format!("[{}]", ORC_RAW);
+    let unknown_other = r#"[{ "name": "player", "default": "hostile", "reactions": { "elves": "neutral" } },
+        { "name": "orcs", "default": "hostile" }]"#;
+    let unknown_reaction = r#"[{ "name": "player", "default": "hostile", "reactions": { "orcs": "grumpy" } },
+        { "name": "orcs", "default": "hostile" }]"#;
+    let no_player = r#"[{ "name": "orcs", "default": "hostile" }]"#;
+
+    let unknown_other = raws_with("unknown_other_raws", &orcs, unknown_other, SPAWN_TABLE_RAW)
+        .expect_err("Reactions to unknown factions should be rejected");
+    let unknown_reaction = raws_with(
+        "unknown_reaction_raws",
+        &orcs,
+        unknown_reaction,
+        SPAWN_TABLE_RAW,
+    )
+    .expect_err("Unknown reactions should be rejected");
+    let no_player = raws_with("no_player_raws", &orcs, no_player, SPAWN_TABLE_RAW)
+        .expect_err("Raws without the player's faction should be rejected");
+
+    assert!(
+        unknown_other.contains(r#"factions[0] ("player"): there is no faction named "elves""#),
+        "{}",
+        unknown_other
+    );
+    assert!(unknown_reaction.contains("grumpy"), "{}", unknown_reaction);
+    assert!(
+        no_player.contains(r#"the "player" faction is missing"#),
+        "{}",
+        no_player
+    );
+}
+
+#[test]
+fn raws_reject_unknown_names_in_the_spawn_table() {
+    let spawn_table = r#"[{ "name": "Orc", "weight": 1, "min_depth": 1 },
+        { "name": "Elixir", "weight": 1, "min_depth": 1 }]"#;
+
+    let err = raws_with(
+        "unknown_spawn_raws",
+        &format!("[{}]", ORC_RAW),
+        FACTIONS_RAW,
+        spawn_table,
+    )
+    .expect_err("Unknown spawn table names should be rejected");
+
+    assert!(
+        err.contains(
+            r#"spawn_table[1] ("Elixir"): there is no monster, companion or item with this name"#
+        ),
+        "{}",
+        err
+    );
+}
+
+/// Leaves only the player, so that the spawned level doesn't get in the way
+fn remove_monsters(sim: &mut Simulation) {
+    let monsters: Vec<Entity> = {
+        let entities = sim.ecs().entities();
+        let monster_chars = sim.ecs().read_storage::<MonsterChar>();
+        let companions = sim.ecs().read_storage::<Companion>();
+        entities
+            .join()
+            .filter(|&ent| monster_chars.contains(ent) || companions.contains(ent))
+            .collect()
+    };
+    sim.ecs_mut()
+        .delete_entities(&monsters)
+        .expect("Should be able to delete the monsters");
+    sim.ecs_mut().maintain();
+}
+
+#[test]
+fn rival_factions_fight_each_other() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    open_field(&mut sim);
+    teleport_player_away(&mut sim, Point::new(40, 20));
+    spawn_orc_at(&mut sim, 40, 20);
+    spawner::spawn_named(sim.ecs_mut(), "Goblin", (43, 20)).expect("Goblin should be in the raws");
+
+    sim.step_n(PlayerCommand::Wait, 6);
+
+    let blows =
+        count_log_entries(&sim, "Orc hits Goblin") + count_log_entries(&sim, "Goblin hits Orc");
+    assert!(blows > 0, "Orc and goblin should have fought");
+}
+
+#[test]
+fn rivals_path_around_walls_to_reach_each_other() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    open_field(&mut sim);
+    place_player(&mut sim, 55, 27);
+    // A wall between the two, with a see-through but blocked window and a door a few tiles up
+    {
+        let mut level = sim.ecs_mut().fetch_mut::<Level>();
+        for y in 1..FIELD_HEIGHT - 1 {
+            if y != 12 && y != 15 {
+                let idx = level.xy_idx(22, y);
+                level.tiles[idx] = TileType::Wall;
+            }
+        }
+        level.index_walls();
+    }
+    sim.ecs_mut()
+        .create_entity()
+        .with(Position { x: 22, y: 15 })
+        .with(TileBlocker {})
+        .build();
+    spawn_orc_at(&mut sim, 20, 15);
+    spawner::spawn_named(sim.ecs_mut(), "Goblin", (25, 15)).expect("Goblin should be in the raws");
+
+    sim.step_n(PlayerCommand::Wait, 20);
+
+    let blows =
+        count_log_entries(&sim, "Orc hits Goblin") + count_log_entries(&sim, "Goblin hits Orc");
+    assert!(blows > 0, "Orc and goblin should have gone round the wall");
+}
+
+#[test]
+fn neutral_creatures_are_left_alone() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    open_field(&mut sim);
+    let (rat, _) = spawn_next_to_player(&mut sim, "Rat");
+    let rat_pos = position_of(&sim, rat);
+    spawn_orc_at(&mut sim, rat_pos.x + 2, rat_pos.y);
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    if let Some(stats) = sim
+        .ecs_mut()
+        .write_storage::<CombatStats>()
+        .get_mut(player_ent)
+    {
+        stats.max_hp = 10_000;
+        stats.hp = 10_000;
+    }
+
+    sim.step_n(PlayerCommand::Wait, 6);
+
+    assert!(count_log_entries(&sim, "Orc hits Player") > 0);
+    assert_eq!(count_log_entries(&sim, "Orc hits Rat"), 0);
+    assert_eq!(count_log_entries(&sim, "Rat hits"), 0);
+}
+
+fn spawn_dog_at(sim: &mut Simulation, x: i32, y: i32) -> Entity {
+    spawner::spawn_named(sim.ecs_mut(), "Dog", (x, y)).expect("Dog should be in the raws")
+}
+
+fn order(sim: &mut Simulation, companion: Entity, order: CompanionOrder, target: Option<Entity>) {
+    sim.step(PlayerCommand::Order {
+        companion,
+        order,
+        target,
+    });
+}
+
+fn order_of(sim: &Simulation, ent: Entity) -> CompanionOrder {
+    sim.ecs()
+        .read_storage::<Companion>()
+        .get(ent)
+        .expect("Entity should be a companion")
+        .order
+}
+
+#[test]
+fn companions_follow_the_player_or_stay_as_told() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    open_field(&mut sim);
+    place_player(&mut sim, 30, 20);
+    let follower = spawn_dog_at(&mut sim, 31, 20);
+    let stayer = spawn_dog_at(&mut sim, 31, 21);
+    // Lets the player see the dogs
+    sim.step(PlayerCommand::Wait);
+
+    order(&mut sim, follower, CompanionOrder::Follow, None);
+    assert_eq!(count_log_entries(&sim, "You tell the Dog to follow you"), 1);
+    assert_eq!(order_of(&sim, follower), CompanionOrder::Follow);
+
+    sim.step_n(PlayerCommand::Move { dx: -1, dy: 0 }, 8);
+
+    let player_pos = *sim.ecs().fetch::<Point>();
+    assert_eq!(player_pos, Point::new(22, 20));
+    let follower_dist = DistanceAlg::Pythagoras.distance2d(position_of(&sim, follower), player_pos);
+    assert!(follower_dist <= 2.0, "Follower is {} away", follower_dist);
+    assert_eq!(position_of(&sim, stayer), Point::new(31, 21));
+}
+
+#[test]
+fn only_companions_in_sight_or_recruited_take_orders() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    open_field(&mut sim);
+    place_player(&mut sim, 10, 15);
+    let dog = spawn_dog_at(&mut sim, 50, 15);
+    sim.step(PlayerCommand::Wait);
+
+    // Nobody told the player about this one
+    assert!(orderable_companions(sim.ecs()).is_empty());
+    order(&mut sim, dog, CompanionOrder::Follow, None);
+    assert_eq!(order_of(&sim, dog), CompanionOrder::Stay);
+
+    place_player(&mut sim, 48, 15);
+    sim.step(PlayerCommand::Wait);
+    order(&mut sim, dog, CompanionOrder::Stay, None);
+    place_player(&mut sim, 10, 15);
+    sim.step(PlayerCommand::Wait);
+
+    // Once recruited, it can be called from afar
+    assert_eq!(orderable_companions(sim.ecs()), vec![dog]);
+    order(&mut sim, dog, CompanionOrder::Follow, None);
+    assert_eq!(order_of(&sim, dog), CompanionOrder::Follow);
+}
+
+#[test]
+fn player_swaps_places_with_a_companion_in_the_way() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    open_field(&mut sim);
+    place_player(&mut sim, 30, 20);
+    let dog = spawn_dog_at(&mut sim, 31, 20);
+    // Lets the level index the dog
+    sim.step(PlayerCommand::Wait);
+
+    sim.step(PlayerCommand::Move { dx: 1, dy: 0 });
+
+    assert_eq!(*sim.ecs().fetch::<Point>(), Point::new(31, 20));
+    assert_eq!(position_of(&sim, dog), Point::new(30, 20));
+    assert_eq!(count_log_entries(&sim, "Player hits Dog"), 0);
+}
+
+#[test]
+fn companion_attacks_whoever_it_is_told_to() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    open_field(&mut sim);
+    place_player(&mut sim, 30, 20);
+    let dog = spawn_dog_at(&mut sim, 31, 20);
+    // Rats are neutral, so the dog would leave it alone on its own
+    let rat =
+        spawner::spawn_named(sim.ecs_mut(), "Rat", (36, 20)).expect("Rat should be in the raws");
+    sim.step(PlayerCommand::Wait);
+
+    order(&mut sim, dog, CompanionOrder::Attack, Some(rat));
+    assert_eq!(
+        count_log_entries(&sim, "You tell the Dog to attack the Rat"),
+        1
+    );
+    sim.step_n(PlayerCommand::Wait, 15);
+
+    assert!(count_log_entries(&sim, "Dog hits Rat") > 0);
+    assert!(!sim.ecs().is_alive(rat));
+    // With the rat dead it goes back to following
+    assert_eq!(order_of(&sim, dog), CompanionOrder::Follow);
+}
+
+#[test]
+fn companions_are_not_sent_after_the_players_friends() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    open_field(&mut sim);
+    place_player(&mut sim, 30, 20);
+    let dog = spawn_dog_at(&mut sim, 31, 20);
+    let other_dog = spawn_dog_at(&mut sim, 33, 20);
+    sim.step(PlayerCommand::Wait);
+
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    order(&mut sim, dog, CompanionOrder::Attack, Some(other_dog));
+    order(&mut sim, dog, CompanionOrder::Attack, Some(player_ent));
+
+    assert_eq!(order_of(&sim, dog), CompanionOrder::Stay);
+    assert_eq!(count_log_entries(&sim, "You tell the Dog to attack"), 0);
+}
+
+#[test]
+fn companion_fights_hostiles_next_to_it() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    open_field(&mut sim);
+    place_player(&mut sim, 30, 20);
+    let dog = spawn_dog_at(&mut sim, 40, 20);
+    spawn_orc_at(&mut sim, 41, 20);
+    if let Some(stats) = sim.ecs_mut().write_storage::<CombatStats>().get_mut(dog) {
+        stats.max_hp = 10_000;
+        stats.hp = 10_000;
+    }
+
+    sim.step_n(PlayerCommand::Wait, 4);
+
+    assert!(count_log_entries(&sim, "Dog hits Orc") > 0);
+}
+
+#[test]
+fn followers_come_along_downstairs_and_stayers_are_left_behind() {
+    let mut sim = new_sim();
+    remove_monsters(&mut sim);
+    let stairs = {
+        let level = sim.ecs().fetch::<Level>();
+        let idx = (0..level.tiles.len())
+            .find(|&idx| level.tiles[idx] == TileType::DownStairs)
+            .expect("Level should have stairs down");
+        level.idx_xy(idx)
+    };
+    place_player(&mut sim, stairs.0, stairs.1);
+    let (follower, _) = spawn_next_to_player(&mut sim, "Dog");
+    let (stayer, _) = spawn_next_to_player(&mut sim, "Dog");
+    sim.step(PlayerCommand::Wait);
+    order(&mut sim, follower, CompanionOrder::Follow, None);
+
+    sim.step(PlayerCommand::Descend);
+
+    assert_eq!(sim.ecs().fetch::<Level>().depth, 2);
+    assert!(sim.ecs().is_alive(follower));
+    assert!(!sim.ecs().is_alive(stayer));
+    assert!(distance_to_player(&sim, follower) < 3.0);
+    assert_eq!(order_of(&sim, follower), CompanionOrder::Follow);
+}
+
+#[test]
+fn broken_saves_are_reported_instead_of_crashing() {
+    let mut sim = new_sim();
+    let path = std::env::temp_dir().join(format!("broken_save_{}.json", std::process::id()));
+    let path = path.to_str().expect("Temp dir should be valid unicode");
+
+    std::fs::write(path, "{ this is not a save").expect("Should be able to write the temp file");
+    let broken = save_load::load_game_from(sim.ecs_mut(), path);
+    std::fs::remove_file(path).expect("Should be able to remove the temp file");
+    let missing = save_load::load_game_from(sim.ecs_mut(), path);
+
+    assert!(broken.is_err());
+    assert!(missing.is_err());
+}
+
+fn name_of(sim: &Simulation, ent: Entity) -> String {
+    sim.ecs()
+        .read_storage::<GameplayName>()
+        .get(ent)
+        .expect("Entity should have a name")
+        .name
+        .clone()
+}
+
+#[test]
+fn saved_runs_resume_with_their_items_still_carried_and_worn() {
+    let mut sim = new_sim();
+    let dagger = carry(&mut sim, "Dagger");
+    carry(&mut sim, "Health Potion");
+    sim.step(PlayerCommand::Equip(dagger));
+    let player_pos = *sim.ecs().fetch::<Point>();
+    let path = std::env::temp_dir().join(format!("round_trip_save_{}.json", std::process::id()));
+    let path = path.to_str().expect("Temp dir should be valid unicode");
+
+    save_load::save_game_to(sim.ecs_mut(), path).expect("Game should be saved");
+    let loaded = save_load::load_game_from(sim.ecs_mut(), path);
+    std::fs::remove_file(path).expect("Should be able to remove the temp file");
+    loaded.expect("Saved game should load");
+
+    // The references were written as markers, so they must point at the reloaded player
+    let player_ent = *sim.ecs().fetch::<Entity>();
+    let mut carried: Vec<String> = {
+        let entities = sim.ecs().entities();
+        let backpack = sim.ecs().read_storage::<InBackpack>();
+        (&entities, &backpack)
+            .join()
+            .filter(|(_, carried)| carried.owner == player_ent)
+            .map(|(ent, _)| name_of(&sim, ent))
+            .collect()
+    };
+    carried.sort();
+    assert_eq!(carried, vec!["Dagger", "Health Potion"]);
+    let worn: Vec<String> = worn_by_player(&sim)
+        .into_iter()
+        .map(|ent| name_of(&sim, ent))
+        .collect();
+    assert_eq!(worn, vec!["Dagger"]);
+    assert_eq!(*sim.ecs().fetch::<Point>(), player_pos);
+    assert!(sim.ecs().read_storage::<SerializationHelper>().is_empty());
+
+    assert_eq!(sim.step(PlayerCommand::Wait), GameStatus::AwaitingInput);
+}
+
+#[test]
+fn unwritable_saves_are_reported_instead_of_crashing() {
+    let mut sim = new_sim();
+
+    let saved = save_load::save_game_to(sim.ecs_mut(), "/nonexistent/dir/save.json");
+
+    assert!(saved.is_err());
+    assert_eq!(sim.step(PlayerCommand::Wait), GameStatus::AwaitingInput);
+}